@@ -4,7 +4,7 @@ export function validateEmail(email: string): boolean {
 }
 
 export function validatePassword(password: string): boolean {
-  return password.length >= 6;
+  return password.length >= 6 && password.length <= 128;
 }
 
 export function validateUrl(url: string): boolean {
@@ -0,0 +1,118 @@
+import * as React from 'react';
+import Button from '@mui/material/Button';
+import TextField from '@mui/material/TextField';
+import Dialog from '@mui/material/Dialog';
+import DialogActions from '@mui/material/DialogActions';
+import DialogContent from '@mui/material/DialogContent';
+import DialogContentText from '@mui/material/DialogContentText';
+import DialogTitle from '@mui/material/DialogTitle';
+import { useSnackbar } from 'notistack';
+import { validatePassword } from '../common/validation';
+
+interface Props {
+  open: boolean;
+  handleClose: () => void;
+}
+
+export default function ChangePasswordDialog(props: Props) {
+  const { open, handleClose } = props;
+
+  const [currentPassword, setCurrentPassword] = React.useState("");
+  const [newPassword, setNewPassword] = React.useState("");
+  const [confirmPassword, setConfirmPassword] = React.useState("");
+
+  const { enqueueSnackbar } = useSnackbar();
+
+  function handleChange(): void {
+    if (!validatePassword(newPassword)) {
+      enqueueSnackbar("Password must have 6 to 128 characters.", {
+        variant: 'error',
+      });
+      return;
+    }
+    if (newPassword != confirmPassword) {
+      enqueueSnackbar("Passwords don't match.", {
+        variant: 'error',
+      });
+      return;
+    }
+
+    fetch("/api/password", {
+      method: 'POST',
+      headers: {
+        'Content-Type': 'application/json'
+      },
+      body: JSON.stringify({
+        current_password: currentPassword,
+        new_password: newPassword,
+      }),
+    })
+      .then((res: Response) => {
+        if (res.status == 204) {
+          enqueueSnackbar("Password changed successfully.", {
+            variant: 'success',
+          });
+          setCurrentPassword("");
+          setNewPassword("");
+          setConfirmPassword("");
+          handleClose();
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+  }
+
+  return (
+    <Dialog open={open} onClose={handleClose}>
+      <DialogTitle>Change Password</DialogTitle>
+      <DialogContent>
+        <DialogContentText>
+          Other devices will be logged out.
+        </DialogContentText>
+        <TextField
+          id="current-password"
+          label="Current Password"
+          type="password"
+          fullWidth
+          variant="standard"
+          margin="dense"
+          value={currentPassword}
+          onChange={(e) => setCurrentPassword(e.target.value)}
+        />
+        <TextField
+          id="new-password"
+          label="New Password"
+          type="password"
+          fullWidth
+          variant="standard"
+          margin="dense"
+          value={newPassword}
+          onChange={(e) => setNewPassword(e.target.value)}
+        />
+        <TextField
+          id="confirm-password"
+          label="Confirm New Password"
+          type="password"
+          fullWidth
+          variant="standard"
+          margin="dense"
+          value={confirmPassword}
+          onChange={(e) => setConfirmPassword(e.target.value)}
+        />
+      </DialogContent>
+      <DialogActions>
+        <Button onClick={handleClose}>Cancel</Button>
+        <Button onClick={handleChange} variant="contained">Change</Button>
+      </DialogActions>
+    </Dialog>
+  );
+}
@@ -0,0 +1,168 @@
+import * as React from 'react';
+import Card from '@mui/material/Card';
+import CardActions from '@mui/material/CardActions';
+import CardContent from '@mui/material/CardContent';
+import Button from '@mui/material/Button';
+import Typography from '@mui/material/Typography';
+import List from '@mui/material/List';
+import ListItem from '@mui/material/ListItem';
+import ListItemText from '@mui/material/ListItemText';
+import IconButton from '@mui/material/IconButton';
+import AutorenewIcon from '@mui/icons-material/Autorenew';
+import ContentCopyIcon from '@mui/icons-material/ContentCopy';
+import DeleteIcon from '@mui/icons-material/Delete';
+import EditIcon from '@mui/icons-material/Edit';
+import { useSnackbar } from 'notistack';
+import Collection from '../models/Collection';
+import FeedInfo from '../models/FeedInfo';
+import EditCollectionDialog from './EditCollectionDialog';
+
+const EMPTY_COLLECTION: Collection = { id: -1, name: '', description: '', feeds: [] }
+
+interface Props {
+  feeds: FeedInfo[];
+}
+
+export default function CollectionsCard(props: Props) {
+  const { feeds } = props;
+
+  const [collections, setCollections] = React.useState([] as Collection[]);
+
+  const [openEditDialog, setOpenEditDialog] = React.useState(false);
+  const [editingCollection, setEditingCollection] = React.useState(EMPTY_COLLECTION);
+
+  // Increse the counter to triggle refreshing the collection list
+  const [editCounter, setEditCounter] = React.useState(1);
+  const refresh = () => setEditCounter(prev => prev + 1);
+
+  const { enqueueSnackbar } = useSnackbar();
+
+  React.useEffect(() => {
+    fetch("/api/collections")
+      .then(res => {
+        if (res.status == 200) {
+          res.json().then((result: any) => setCollections(result as Collection[]))
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          })
+        }
+      })
+      .catch((error: any) => {
+        console.error(error);
+      })
+  }, [editCounter])
+
+  const addCollection = () => {
+    setEditingCollection(EMPTY_COLLECTION);
+    setOpenEditDialog(true);
+  }
+  const editCollection = (collection: Collection) => {
+    setEditingCollection(collection);
+    setOpenEditDialog(true);
+  }
+
+  const copyUrl = (collection: Collection) => {
+    navigator.clipboard.writeText(window.location.origin + `/rss?token=${collection.token}`);
+    enqueueSnackbar("URL copied to clipboard.", {
+      variant: 'success',
+    });
+  }
+
+  // The old URL stops working right away, e.g. after it leaked
+  const regenerateUrl = (id: number) => {
+    fetch(`/api/collections/${id}/token`, {
+      method: 'POST',
+    })
+      .then((res: Response) => {
+        if (res.status == 204) {
+          enqueueSnackbar("URL regenerated successfully.", {
+            variant: 'success',
+          });
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+        refresh();
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+  };
+
+  const deleteCollection = (id: number) => {
+    fetch(`/api/collections/${id}`, {
+      method: 'DELETE',
+    })
+      .then((res: Response) => {
+        if (res.status == 204) {
+          enqueueSnackbar("Collection deleted successfully.", {
+            variant: 'success',
+          });
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+        refresh();
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+  };
+
+  return (
+    <Card>
+      <CardContent>
+        <Typography sx={{ fontSize: 14 }} color="text.secondary" gutterBottom>
+          Collections
+        </Typography>
+        <List dense>
+          {collections.map((collection: Collection) => (
+            <ListItem key={collection.id} sx={{ paddingX: 0 }}>
+              <ListItemText
+                primary={collection.name}
+                secondary={`${collection.feeds.length} feed(s)` + (collection.description ? ` · ${collection.description}` : '')}
+              />
+              <IconButton aria-label="copy" onClick={() => copyUrl(collection)}>
+                <ContentCopyIcon />
+              </IconButton>
+              <IconButton aria-label="regenerate" onClick={() => regenerateUrl(collection.id)}>
+                <AutorenewIcon />
+              </IconButton>
+              <IconButton aria-label="edit" onClick={() => editCollection(collection)}>
+                <EditIcon />
+              </IconButton>
+              <IconButton edge="end" aria-label="delete" onClick={() => deleteCollection(collection.id)}>
+                <DeleteIcon />
+              </IconButton>
+            </ListItem>
+          ))}
+        </List>
+      </CardContent>
+      <CardActions>
+        <Button size="small" onClick={addCollection}>New Collection</Button>
+      </CardActions>
+
+      <EditCollectionDialog
+        open={openEditDialog}
+        handleClose={() => setOpenEditDialog(false)}
+        collection={editingCollection}
+        setCollection={setEditingCollection}
+        feeds={feeds}
+        refreshCollectionList={refresh}
+      />
+    </Card>
+  );
+}
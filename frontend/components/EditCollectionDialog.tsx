@@ -0,0 +1,118 @@
+import * as React from 'react';
+import Button from '@mui/material/Button';
+import TextField from '@mui/material/TextField';
+import Dialog from '@mui/material/Dialog';
+import DialogActions from '@mui/material/DialogActions';
+import DialogContent from '@mui/material/DialogContent';
+import DialogContentText from '@mui/material/DialogContentText';
+import DialogTitle from '@mui/material/DialogTitle';
+import FormGroup from '@mui/material/FormGroup';
+import FormControlLabel from '@mui/material/FormControlLabel';
+import Checkbox from '@mui/material/Checkbox';
+import Collection from '../models/Collection';
+import FeedInfo from '../models/FeedInfo';
+import { useSnackbar } from 'notistack';
+
+interface Props {
+  open: boolean;
+  handleClose: () => void;
+  collection: Collection;
+  setCollection: React.Dispatch<React.SetStateAction<Collection>>;
+  feeds: FeedInfo[];
+  refreshCollectionList: () => void;
+}
+
+export default function EditCollectionDialog(props: Props) {
+  const { open, handleClose, collection, setCollection, feeds, refreshCollectionList } = props;
+  const isNew = collection.id === -1;
+
+  const { enqueueSnackbar } = useSnackbar();
+
+  const setName = (name: string) => setCollection(prev => ({ ...prev, name }));
+  const setDescription = (description: string) => setCollection(prev => ({ ...prev, description }));
+  const toggleFeed = (id: number, checked: boolean) => setCollection(prev => ({
+    ...prev,
+    feeds: checked ? [...prev.feeds, id] : prev.feeds.filter(feed => feed !== id),
+  }));
+
+  const handleSubmit = () => {
+    let endpoint: string;
+    if (isNew) {
+      endpoint = "/api/collections"; // create
+    } else {
+      endpoint = `/api/collections/${collection.id}`; // update
+    }
+
+    fetch(endpoint, {
+      method: 'POST',
+      body: JSON.stringify(collection),
+    })
+      .then((res: Response) => {
+        if (res.status == 201 || res.status == 204) {
+          enqueueSnackbar("Collection " + (isNew ? "added" : "edited") + " successfully.", {
+            variant: 'success',
+          });
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+        refreshCollectionList();
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+    handleClose();
+  }
+
+  return (
+    <Dialog open={open} onClose={handleClose}>
+      <DialogTitle>{isNew ? "Add" : "Edit"} Collection</DialogTitle>
+      <DialogContent>
+        <DialogContentText>
+          A collection merges the chosen feeds into a separate RSS feed with its own URL.
+        </DialogContentText>
+        <TextField
+          margin="dense"
+          id="name"
+          label="Name"
+          type="text"
+          fullWidth
+          variant="standard"
+          value={collection.name}
+          onChange={(e) => setName(e.target.value)}
+        />
+        <TextField
+          margin="dense"
+          id="description"
+          label="Description (Optional)"
+          type="text"
+          fullWidth
+          variant="standard"
+          value={collection.description}
+          onChange={(e) => setDescription(e.target.value)}
+        />
+        <FormGroup>
+          {feeds.map((feed: FeedInfo) => (
+            <FormControlLabel
+              key={feed.id}
+              control={<Checkbox
+                checked={collection.feeds.includes(feed.id)}
+                onChange={(e) => toggleFeed(feed.id, e.target.checked)}
+              />}
+              label={feed.name}
+            />
+          ))}
+        </FormGroup>
+      </DialogContent>
+      <DialogActions>
+        <Button onClick={handleClose}>Cancel</Button>
+        <Button variant='contained' onClick={handleSubmit}>Submit</Button>
+      </DialogActions>
+    </Dialog>
+  );
+}
@@ -9,24 +9,15 @@ import DialogTitle from '@mui/material/DialogTitle';
 import FeedPreviewTable from './FeedPreviewTable';
 import FeedItem from '../models/FeedItem';
 import Tooltip from '@mui/material/Tooltip';
+import FormControlLabel from '@mui/material/FormControlLabel';
+import Checkbox from '@mui/material/Checkbox';
 import Zoom from '@mui/material/Zoom';
 import FeedInfo from '../models/FeedInfo';
 import { useSnackbar } from 'notistack';
 import { validateUrl } from '../common/validation';
 
-function filterItems(items: FeedItem[], keywords: string | string[]): FeedItem[] {
-  if (typeof keywords === 'string') {
-    keywords = keywords.split(' ').filter(s => s.length > 0)
-  }
-  return items.filter(item => {
-    for (var keyword of keywords) {
-      if (item.title.indexOf(keyword) == -1) {
-        return false;
-      }
-    }
-    return true;
-  })
-}
+// Delay of fetching the preview after the last edit
+const PREVIEW_DELAY = 500;
 
 interface Props {
   open: boolean;
@@ -45,18 +36,34 @@ export default function EditFeedDialog(props: Props) {
   const setUrl = (url: string) => setFeed(prev => ({ ...prev, url }));
   const setName = (name: string) => setFeed(prev => ({ ...prev, name }));
   const setKeywords = (keywords: string) => setFeed(prev => ({ ...prev, keywords }));
+  const setRegex = (regex: string) => setFeed(prev => ({ ...prev, regex }));
+  const setIgnoreCase = (ignore_case: boolean) => setFeed(prev => ({ ...prev, ignore_case }));
+  const setNormalize = (normalize: boolean) => setFeed(prev => ({ ...prev, normalize }));
+  const setTimezone = (timezone: string) => setFeed(prev => ({ ...prev, timezone }));
 
   // Control the Preview dialog
   const [showPreview, setShowPreview] = React.useState(false);
 
-  // State of fetched items
-  const [fetchedItems, setFetchedItems] = React.useState([] as FeedItem[]);
+  // State of fetched items, which are filtered by the server
+  const [previewItems, setPreviewItems] = React.useState([] as FeedItem[]);
+
+  // URL of the last fetch, to notify only when the URL changes
+  const fetchedUrl = React.useRef("");
 
   const validUrl = validateUrl(feed.url);
 
   React.useEffect(() => {
-    if (feed.url && validUrl) {
-      fetch(`/api/fetch?url=${encodeURIComponent(feed.url)}`)
+    if (!feed.url || !validUrl) {
+      setPreviewItems([]);
+      return;
+    }
+    const timer = setTimeout(() => {
+      // Stored feeds are previewed with their stored keyword syntax
+      const endpoint = isNew ? "/api/fetch" : `/api/fetch?id=${feed.id}`;
+      fetch(endpoint, {
+        method: 'POST',
+        body: JSON.stringify(feed),
+      })
         .then(res => {
           if (res.status == 200) {
             res.json().then((result: any) => {
@@ -68,17 +75,20 @@ export default function EditFeedDialog(props: Props) {
                 title: item.title,
                 link: item.link,
               }));
-              setFetchedItems(items);
-              enqueueSnackbar("Fetched RSS feed successfully.", {
-                variant: 'success'
-              })
+              setPreviewItems(items);
+              if (fetchedUrl.current !== feed.url) {
+                fetchedUrl.current = feed.url;
+                enqueueSnackbar("Fetched RSS feed successfully.", {
+                  variant: 'success'
+                })
+              }
             })
           } else {
             res.text().then((message) => {
               enqueueSnackbar(message, {
                 variant: 'error',
               });
-              setFetchedItems([]);
+              setPreviewItems([]);
             })
           }
         })
@@ -86,16 +96,12 @@ export default function EditFeedDialog(props: Props) {
           enqueueSnackbar(error.toString(), {
             variant: 'error',
           });
-          setFetchedItems([]);
+          setPreviewItems([]);
           console.error(error);
         })
-    } else {
-      setFetchedItems([]);
-    }
-  }, [feed.url])
-
-  // Filter fetched items by user-specified keywords
-  const previewItems = filterItems(fetchedItems, feed.keywords);
+    }, PREVIEW_DELAY);
+    return () => clearTimeout(timer);
+  }, [feed.url, feed.keywords, feed.regex, feed.rules, feed.ignore_case, feed.normalize, feed.timezone])
 
   const handleSubmit = () => {
     if (!validUrl) {
@@ -146,6 +152,8 @@ export default function EditFeedDialog(props: Props) {
           <DialogContentText>
             To subscribe to an RSS feed, please enter the URL here. You may optinally filter the results
             with keywords, which must appear in the title of items.
+            Use <code>OR</code>, <code>-</code> (exclude), <code>"quoted phrases"</code> and parentheses
+            for complex conditions, e.g. <code>"1080p" (简体 OR 繁體) -合集</code>.
           </DialogContentText>
           <TextField
             margin="dense"
@@ -181,6 +189,40 @@ export default function EditFeedDialog(props: Props) {
               onChange={(e) => setKeywords(e.target.value)}
             />
           </Tooltip>
+          <Tooltip TransitionComponent={Zoom} title="Regular expression which titles must match" arrow>
+            <TextField
+              margin="dense"
+              id="regex"
+              label="Filter Regex (Optional)"
+              type="text"
+              fullWidth
+              variant="standard"
+              value={feed.regex ?? ""}
+              onChange={(e) => setRegex(e.target.value)}
+            />
+          </Tooltip>
+          <FormControlLabel
+            control={<Checkbox checked={feed.ignore_case ?? false} onChange={(e) => setIgnoreCase(e.target.checked)} />}
+            label="Ignore case"
+          />
+          <Tooltip TransitionComponent={Zoom} title="Treat full-width and half-width characters alike, e.g. １０８０Ｐ and 1080P" arrow>
+            <FormControlLabel
+              control={<Checkbox checked={feed.normalize ?? false} onChange={(e) => setNormalize(e.target.checked)} />}
+              label="Normalize width"
+            />
+          </Tooltip>
+          <Tooltip TransitionComponent={Zoom} title="For dates without offsets, e.g. Asia/Shanghai. Defaults to UTC" arrow>
+            <TextField
+              margin="dense"
+              id="timezone"
+              label="Timezone (Optional)"
+              type="text"
+              fullWidth
+              variant="standard"
+              value={feed.timezone ?? ""}
+              onChange={(e) => setTimezone(e.target.value)}
+            />
+          </Tooltip>
         </DialogContent>
         <DialogActions>
           <Button onClick={() => setShowPreview(true)} disabled={previewItems.length === 0}>
@@ -69,6 +69,44 @@ export default function LoginDialog(props: Props) {
       });
   }
 
+  function handleForgotPassword(): void {
+    if (!validateEmail(email)) {
+      enqueueSnackbar("Please fill in a valid E-mail first.", {
+        variant: 'error',
+      });
+      return;
+    }
+
+    fetch("/api/password/reset", {
+      method: 'POST',
+      headers: {
+        'Content-Type': 'application/json'
+      },
+      body: JSON.stringify({
+        email: email,
+      }),
+    })
+      .then((res: Response) => {
+        if (res.status == 204) {
+          enqueueSnackbar("If the E-mail is registered, a link to reset the password has been sent to it.", {
+            variant: 'success',
+          });
+          handleClose();
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+  }
+
   return (
     <Dialog open={open} onClose={handleClose}>
       <DialogTitle>Login</DialogTitle>
@@ -96,6 +134,7 @@ export default function LoginDialog(props: Props) {
       </DialogContent>
       <DialogActions>
         <Button onClick={switchToRegister}>Register</Button>
+        <Button onClick={handleForgotPassword}>Forgot Password</Button>
         <div style={{ flex: '1 0 0' }} />
         <Button onClick={handleClose}>Cancel</Button>
         <Button onClick={handleLogin} variant="contained">Login</Button>
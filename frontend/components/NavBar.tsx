@@ -6,6 +6,8 @@ import Typography from '@mui/material/Typography';
 import Button from '@mui/material/Button';
 import LoginDialog from './LoginDialog';
 import RegisterDialog from './RegisterDialog';
+import ChangePasswordDialog from './ChangePasswordDialog';
+import ResetPasswordDialog from './ResetPasswordDialog';
 import User from '../models/User';
 import Avatar from '@mui/material/Avatar';
 import gravatar from 'gravatar'
@@ -31,6 +33,7 @@ export default function MyAppBar(props: Props) {
   const { user, setUser, openLogin, setOpenLogin, openRegister, setOpenRegister } = props;
 
   const [anchorEl, setAnchorEl] = React.useState<null | HTMLElement>(null);
+  const [openChangePassword, setOpenChangePassword] = React.useState(false);
 
   const { enqueueSnackbar, closeSnackbar } = useSnackbar();
   const router = useRouter();
@@ -117,6 +120,10 @@ export default function MyAppBar(props: Props) {
                   Report an issue
                 </MenuItem>
               </MuiLink>
+              <MenuItem onClick={() => {
+                setOpenChangePassword(true);
+                setAnchorEl(null);
+              }}>Change password</MenuItem>
               <MenuItem onClick={handleLogOut}>Log out</MenuItem>
             </Menu>
           }
@@ -137,6 +144,19 @@ export default function MyAppBar(props: Props) {
             }}
             setUser={setUser}
           />
+          <ChangePasswordDialog
+            open={openChangePassword}
+            handleClose={() => setOpenChangePassword(false)}
+          />
+          {/* Opened by the link of password reset mails */}
+          <ResetPasswordDialog
+            token={router.query.reset_token as string | undefined}
+            handleClose={() => router.replace('/')}
+            switchToLogin={() => {
+              router.replace('/');
+              setOpenLogin(true);
+            }}
+          />
         </Toolbar>
       </AppBar>
     </Box>
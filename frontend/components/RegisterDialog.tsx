@@ -36,7 +36,7 @@ export default function RegisterDialog(props: Props) {
       return;
     }
     if (!validatePassword(password)) {
-      enqueueSnackbar("Password must be at least 6 characters", {
+      enqueueSnackbar("Password must have 6 to 128 characters.", {
         variant: 'error',
       });
       return;
@@ -0,0 +1,102 @@
+import * as React from 'react';
+import Button from '@mui/material/Button';
+import TextField from '@mui/material/TextField';
+import Dialog from '@mui/material/Dialog';
+import DialogActions from '@mui/material/DialogActions';
+import DialogContent from '@mui/material/DialogContent';
+import DialogTitle from '@mui/material/DialogTitle';
+import { useSnackbar } from 'notistack';
+import { validatePassword } from '../common/validation';
+
+interface Props {
+  // Token of the mailed reset link
+  token?: string;
+  handleClose: () => void;
+  switchToLogin: () => void;
+}
+
+export default function ResetPasswordDialog(props: Props) {
+  const { token, handleClose, switchToLogin } = props;
+
+  const [newPassword, setNewPassword] = React.useState("");
+  const [confirmPassword, setConfirmPassword] = React.useState("");
+
+  const { enqueueSnackbar } = useSnackbar();
+
+  function handleReset(): void {
+    if (!validatePassword(newPassword)) {
+      enqueueSnackbar("Password must have 6 to 128 characters.", {
+        variant: 'error',
+      });
+      return;
+    }
+    if (newPassword != confirmPassword) {
+      enqueueSnackbar("Passwords don't match.", {
+        variant: 'error',
+      });
+      return;
+    }
+
+    fetch("/api/password/reset/confirm", {
+      method: 'POST',
+      headers: {
+        'Content-Type': 'application/json'
+      },
+      body: JSON.stringify({
+        token: token,
+        new_password: newPassword,
+      }),
+    })
+      .then((res: Response) => {
+        if (res.status == 204) {
+          enqueueSnackbar("Password reset successfully, please log in again.", {
+            variant: 'success',
+          });
+          switchToLogin();
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+  }
+
+  return (
+    <Dialog open={Boolean(token)} onClose={handleClose}>
+      <DialogTitle>Reset Password</DialogTitle>
+      <DialogContent>
+        <TextField
+          id="new-password"
+          label="New Password"
+          type="password"
+          fullWidth
+          variant="standard"
+          margin="dense"
+          value={newPassword}
+          onChange={(e) => setNewPassword(e.target.value)}
+        />
+        <TextField
+          id="confirm-password"
+          label="Confirm New Password"
+          type="password"
+          fullWidth
+          variant="standard"
+          margin="dense"
+          value={confirmPassword}
+          onChange={(e) => setConfirmPassword(e.target.value)}
+        />
+      </DialogContent>
+      <DialogActions>
+        <Button onClick={handleClose}>Cancel</Button>
+        <Button onClick={handleReset} variant="contained">Reset</Button>
+      </DialogActions>
+    </Dialog>
+  );
+}
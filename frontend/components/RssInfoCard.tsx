@@ -6,6 +6,7 @@ import CardContent from '@mui/material/CardContent';
 import Button from '@mui/material/Button';
 import Typography from '@mui/material/Typography';
 import TextField from '@mui/material/TextField';
+import MenuItem from '@mui/material/MenuItem';
 import ContentCopyIcon from '@mui/icons-material/ContentCopy';
 import IconButton from '@mui/material/IconButton';
 import CloseIcon from '@mui/icons-material/Close';
@@ -15,8 +16,18 @@ interface Props {
   token: string;
 }
 
+// Hours for which the old URL keeps working after regenerating
+const GRACE_PERIODS = [
+  { hours: 0, label: 'Stops now' },
+  { hours: 24, label: 'Works for 1 day' },
+  { hours: 168, label: 'Works for 7 days' },
+];
+
 export default function RssInfoCard(props: Props) {
-  const { token } = props;
+  const [token, setToken] = React.useState(props.token);
+  React.useEffect(() => setToken(props.token), [props.token]);
+
+  const [graceHours, setGraceHours] = React.useState(0);
 
   const [url, setUrl] = React.useState("");
   React.useEffect(() => {
@@ -33,6 +44,33 @@ export default function RssInfoCard(props: Props) {
     });
   }
 
+  const onClickRegenerate = () => {
+    fetch(`/api/token?grace_hours=${graceHours}`, {
+      method: 'POST',
+    })
+      .then((res: Response) => {
+        if (res.status == 200) {
+          res.json().then((user: any) => {
+            setToken(user.token);
+            enqueueSnackbar("URL regenerated successfully.", {
+              variant: 'success',
+            });
+          })
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+  }
+
   return (
     <Card>
       <CardContent>
@@ -55,6 +93,20 @@ export default function RssInfoCard(props: Props) {
           />
         </Box>
       </CardContent>
+      <CardActions>
+        <Button size="small" color="error" onClick={onClickRegenerate}>Regenerate URL</Button>
+        <TextField
+          select
+          size="small"
+          label="Old URL"
+          value={graceHours}
+          onChange={(e) => setGraceHours(Number(e.target.value))}
+        >
+          {GRACE_PERIODS.map((period) => (
+            <MenuItem key={period.hours} value={period.hours}>{period.label}</MenuItem>
+          ))}
+        </TextField>
+      </CardActions>
     </Card >
   );
 }
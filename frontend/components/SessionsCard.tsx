@@ -0,0 +1,97 @@
+import * as React from 'react';
+import Card from '@mui/material/Card';
+import CardActions from '@mui/material/CardActions';
+import CardContent from '@mui/material/CardContent';
+import Button from '@mui/material/Button';
+import Typography from '@mui/material/Typography';
+import List from '@mui/material/List';
+import ListItem from '@mui/material/ListItem';
+import ListItemText from '@mui/material/ListItemText';
+import IconButton from '@mui/material/IconButton';
+import LogoutIcon from '@mui/icons-material/Logout';
+import { useSnackbar } from 'notistack';
+import Session from '../models/Session';
+
+export default function SessionsCard() {
+  const [sessions, setSessions] = React.useState([] as Session[]);
+
+  // Increse the counter to triggle refreshing the session list
+  const [revokeCounter, setRevokeCounter] = React.useState(1);
+  const refresh = () => setRevokeCounter(prev => prev + 1);
+
+  const { enqueueSnackbar } = useSnackbar();
+
+  React.useEffect(() => {
+    fetch("/api/sessions")
+      .then(res => {
+        if (res.status == 200) {
+          res.json().then((result: any) => setSessions(result as Session[]))
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          })
+        }
+      })
+      .catch((error: any) => {
+        console.error(error);
+      })
+  }, [revokeCounter])
+
+  // Revoking the session of this browser logs it out
+  const revoke = (url: string, loggedOut: boolean) => {
+    fetch(url, {
+      method: 'DELETE',
+    })
+      .then((res: Response) => {
+        if (res.status == 204) {
+          if (loggedOut) {
+            window.location.assign('/');
+            return;
+          }
+          enqueueSnackbar("Logged out successfully.", {
+            variant: 'success',
+          });
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          });
+        }
+        refresh();
+      })
+      .catch((error: any) => {
+        enqueueSnackbar(error.toString(), {
+          variant: 'error',
+        });
+      });
+  };
+
+  return (
+    <Card>
+      <CardContent>
+        <Typography sx={{ fontSize: 14 }} color="text.secondary" gutterBottom>
+          Devices
+        </Typography>
+        <List dense>
+          {sessions.map((session: Session) => (
+            <ListItem key={session.id} sx={{ paddingX: 0 }}>
+              <ListItemText
+                primary={(session.user_agent || 'Unknown device') + (session.current ? ' (this device)' : '')}
+                secondary={`Last active ${new Date(session.last_seen_at).toLocaleString()}`}
+              />
+              <IconButton edge="end" aria-label="log out" onClick={() => revoke(`/api/sessions/${session.id}`, session.current)}>
+                <LogoutIcon />
+              </IconButton>
+            </ListItem>
+          ))}
+        </List>
+      </CardContent>
+      <CardActions>
+        <Button size="small" color="error" onClick={() => revoke("/api/sessions", true)}>Log Out Everywhere</Button>
+      </CardActions>
+    </Card>
+  );
+}
@@ -0,0 +1,76 @@
+import * as React from 'react';
+import Card from '@mui/material/Card';
+import CardContent from '@mui/material/CardContent';
+import Typography from '@mui/material/Typography';
+import FormGroup from '@mui/material/FormGroup';
+import FormControlLabel from '@mui/material/FormControlLabel';
+import Checkbox from '@mui/material/Checkbox';
+import { useSnackbar } from 'notistack';
+import UserSettings from '../models/UserSettings';
+
+const DEFAULT_SETTINGS: UserSettings = { dedup_titles: false, prefix_titles: false }
+
+export default function SettingsCard() {
+  const [settings, setSettings] = React.useState(DEFAULT_SETTINGS);
+
+  const { enqueueSnackbar } = useSnackbar();
+
+  React.useEffect(() => {
+    fetch("/api/settings")
+      .then(res => {
+        if (res.status == 200) {
+          res.json().then((result: any) => setSettings(result as UserSettings))
+        }
+      })
+      .catch((error: any) => {
+        console.error(error);
+      })
+  }, [])
+
+  const updateSettings = (newSettings: UserSettings) => {
+    fetch("/api/settings", {
+      method: 'POST',
+      body: JSON.stringify(newSettings),
+    })
+      .then(res => {
+        if (res.status == 204) {
+          setSettings(newSettings);
+        } else {
+          res.text().then((message) => {
+            enqueueSnackbar(message, {
+              variant: 'error',
+            });
+          })
+        }
+      })
+      .catch((error: any) => {
+        console.error(error);
+      })
+  }
+
+  return (
+    <Card>
+      <CardContent>
+        <Typography sx={{ fontSize: 14 }} color="text.secondary" gutterBottom>
+          Settings
+        </Typography>
+        <FormGroup>
+          <FormControlLabel
+            control={<Checkbox
+              checked={settings.dedup_titles}
+              onChange={(e) => updateSettings({ ...settings, dedup_titles: e.target.checked })}
+            />}
+            label="Merge items with the same title across feeds"
+          />
+          <FormControlLabel
+            control={<Checkbox
+              checked={settings.prefix_titles}
+              onChange={(e) => updateSettings({ ...settings, prefix_titles: e.target.checked })}
+            />}
+            label="Prefix item titles with the feed name"
+          />
+        </FormGroup>
+      </CardContent>
+    </Card>
+  );
+}
@@ -0,0 +1,7 @@
+export default interface Collection {
+  id: number,
+  name: string,
+  description: string,
+  token?: string,
+  feeds: number[],
+}
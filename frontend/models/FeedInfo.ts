@@ -1,6 +1,13 @@
+import FilterRule from './FilterRule';
+
 export default interface FeedInfo {
   id: number;
   name: string;
   url: string;
   keywords: string;
+  regex?: string;
+  rules?: FilterRule[];
+  ignore_case?: boolean;
+  normalize?: boolean;
+  timezone?: string;
 }
@@ -0,0 +1,6 @@
+export default interface FilterRule {
+  field: 'title' | 'description' | 'category' | 'author';
+  keywords: string;
+  exclude?: boolean;
+  keep_missing?: boolean;
+}
@@ -0,0 +1,8 @@
+export default interface Session {
+  id: number,
+  user_agent?: string,
+  created_at: string,
+  last_seen_at: string,
+  expires_at: string,
+  current: boolean,
+}
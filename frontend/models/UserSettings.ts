@@ -0,0 +1,4 @@
+export default interface UserSettings {
+  dedup_titles: boolean,
+  prefix_titles: boolean,
+}
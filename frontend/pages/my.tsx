@@ -5,6 +5,9 @@ import Box from '@mui/material/Box';
 import FeedList from '../components/FeedList'
 import AddFeedDialog from '../components/EditFeedDialog';
 import RssInfoCard from '../components/RssInfoCard'
+import SettingsCard from '../components/SettingsCard'
+import CollectionsCard from '../components/CollectionsCard'
+import SessionsCard from '../components/SessionsCard'
 import Fab from '@mui/material/Fab';
 import AddIcon from '@mui/icons-material/Add';
 import FeedInfo from '../models/FeedInfo';
@@ -74,6 +77,21 @@ const MyFeeds: NextPage<Props> = (props: Props) => {
         }}>
           <RssInfoCard token={user.token} />
         </Box>
+        <Box sx={{
+          my: 2,
+        }}>
+          <SettingsCard />
+        </Box>
+        <Box sx={{
+          my: 2,
+        }}>
+          <CollectionsCard feeds={feeds} />
+        </Box>
+        <Box sx={{
+          my: 2,
+        }}>
+          <SessionsCard />
+        </Box>
       </Container>
 
       <Container maxWidth="lg">
@@ -0,0 +1,22 @@
+ALTER TABLE feeds ADD COLUMN fetched_at DATETIME;
+
+ALTER TABLE feeds ADD COLUMN fetch_error VARCHAR;
+
+CREATE TABLE items (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    feed_id    INTEGER  NOT NULL REFERENCES feeds (id) ON DELETE CASCADE,
+    guid       VARCHAR  NOT NULL,
+    pub_date   DATETIME,
+    data       TEXT     NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX items_feed_id_guid_uindex ON items (feed_id, guid);
+
+CREATE INDEX items_pub_date_index ON items (pub_date);
+
+CREATE TRIGGER items_update AFTER UPDATE ON items
+BEGIN
+    UPDATE items SET updated_at = CURRENT_TIMESTAMP WHERE rowid == NEW.rowid;
+END;
@@ -0,0 +1,3 @@
+ALTER TABLE feeds ADD COLUMN etag VARCHAR;
+
+ALTER TABLE feeds ADD COLUMN last_modified VARCHAR;
@@ -0,0 +1,2 @@
+-- JSON array of filter rules, see `FilterRule`
+ALTER TABLE feeds ADD COLUMN rules TEXT NOT NULL DEFAULT '[]';
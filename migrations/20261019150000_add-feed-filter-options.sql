@@ -0,0 +1,3 @@
+ALTER TABLE feeds ADD COLUMN ignore_case BOOLEAN NOT NULL DEFAULT 0;
+
+ALTER TABLE feeds ADD COLUMN normalize BOOLEAN NOT NULL DEFAULT 0;
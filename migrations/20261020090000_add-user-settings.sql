@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN dedup_titles BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN prefix_titles BOOLEAN NOT NULL DEFAULT 0;
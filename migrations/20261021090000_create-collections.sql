@@ -0,0 +1,24 @@
+CREATE TABLE collections (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id     INTEGER  NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name        VARCHAR  NOT NULL,
+    description VARCHAR  NOT NULL DEFAULT '',
+    token       VARCHAR  NOT NULL,
+    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX collections_token_uindex ON collections (token);
+
+CREATE INDEX collections_user_id_index ON collections (user_id);
+
+CREATE TRIGGER collections_update AFTER UPDATE ON collections
+BEGIN
+    UPDATE collections SET updated_at = CURRENT_TIMESTAMP WHERE rowid == NEW.rowid;
+END;
+
+CREATE TABLE collection_feeds (
+    collection_id INTEGER NOT NULL REFERENCES collections (id) ON DELETE CASCADE,
+    feed_id       INTEGER NOT NULL REFERENCES feeds (id) ON DELETE CASCADE,
+    PRIMARY KEY (collection_id, feed_id)
+);
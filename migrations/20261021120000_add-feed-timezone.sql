@@ -0,0 +1,4 @@
+ALTER TABLE feeds ADD COLUMN timezone VARCHAR;
+
+-- Dates of mikanani.me used to be parsed as China Standard Time implicitly
+UPDATE feeds SET timezone = 'Asia/Shanghai' WHERE url LIKE '%://mikanani.me/%';
@@ -0,0 +1,13 @@
+CREATE TABLE sessions (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id      INTEGER  NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token        VARCHAR  NOT NULL,
+    user_agent   VARCHAR,
+    created_at   DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at   DATETIME NOT NULL
+);
+
+CREATE UNIQUE INDEX sessions_token_uindex ON sessions (token);
+
+CREATE INDEX sessions_user_id_index ON sessions (user_id);
@@ -0,0 +1,5 @@
+ALTER TABLE users ADD COLUMN previous_token VARCHAR;
+
+ALTER TABLE users ADD COLUMN previous_token_expires_at DATETIME;
+
+CREATE INDEX users_previous_token_index ON users (previous_token);
@@ -0,0 +1,11 @@
+CREATE TABLE password_resets (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id    INTEGER  NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token_hash VARCHAR  NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at DATETIME NOT NULL
+);
+
+CREATE UNIQUE INDEX password_resets_token_hash_uindex ON password_resets (token_hash);
+
+CREATE INDEX password_resets_user_id_index ON password_resets (user_id);
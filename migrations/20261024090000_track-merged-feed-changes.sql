@@ -0,0 +1,12 @@
+-- Merged feeds change without any newer item when items or whole feeds are
+-- deleted, which is recorded as a change of the user. Changes of settings and
+-- collections bump `updated_at` of their rows already.
+CREATE TRIGGER feeds_delete AFTER DELETE ON feeds
+BEGIN
+    UPDATE users SET updated_at = CURRENT_TIMESTAMP WHERE id == OLD.user_id;
+END;
+
+CREATE TRIGGER items_delete AFTER DELETE ON items
+BEGIN
+    UPDATE users SET updated_at = CURRENT_TIMESTAMP WHERE id == (SELECT user_id FROM feeds WHERE id == OLD.feed_id);
+END;
@@ -0,0 +1,5 @@
+-- Keywords stored before boolean keyword queries are plain lists separated by
+-- spaces, which are matched as such until they are edited
+ALTER TABLE feeds ADD COLUMN legacy_keywords BOOLEAN NOT NULL DEFAULT 0;
+
+UPDATE feeds SET legacy_keywords = 1 WHERE keywords != '';
@@ -0,0 +1,5 @@
+ALTER TABLE collections ADD COLUMN previous_token VARCHAR;
+
+ALTER TABLE collections ADD COLUMN previous_token_expires_at DATETIME;
+
+CREATE INDEX collections_previous_token_index ON collections (previous_token);
@@ -7,6 +7,12 @@ pub enum MalformedFeedError {
     #[error("parse error: {0}")]
     Parse(#[from] xmltree::ParseError),
 
+    #[error("JSON parse error: {0}")]
+    JsonParse(#[from] rocket::serde::json::serde_json::Error),
+
+    #[error("unknown feed format with root element <{0}>")]
+    UnknownFormat(String),
+
     #[error("tag <{0}> not found")]
     TagNotFound(&'static str),
 
@@ -14,6 +20,33 @@ pub enum MalformedFeedError {
     InvalidTag(&'static str),
 }
 
+#[derive(Error, Debug)]
+pub enum InvalidFilterError {
+    #[error("unexpected `{0}` at column {1}")]
+    UnexpectedToken(String, usize),
+
+    #[error("unclosed quote at column {0}")]
+    UnclosedQuote(usize),
+
+    #[error("unclosed parenthesis at column {0}")]
+    UnclosedParenthesis(usize),
+
+    #[error("missing keyword after column {0}")]
+    MissingKeyword(usize),
+
+    #[error("empty phrase at column {0}")]
+    EmptyPhrase(usize),
+
+    #[error("nested too deeply at column {0}")]
+    TooDeep(usize),
+
+    #[error("{0}")]
+    Regex(#[from] regex::Error),
+
+    #[error("rule {0} has no keywords")]
+    EmptyRule(usize),
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Malformed feed: {0}")]
@@ -28,12 +61,44 @@ pub enum Error {
     #[error("Database error")]
     Database(#[from] sqlx::error::Error),
 
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(#[from] InvalidFilterError),
+
+    #[error("Invalid timezone: {0}")]
+    InvalidTimezone(String),
+
     #[error("Feed {0} not found")]
     FeedNotFound(i64),
 
+    #[error("Collection {0} not found")]
+    CollectionNotFound(i64),
+
+    #[error("Session {0} not found")]
+    SessionNotFound(i64),
+
+    #[error("Send mail error: {0}")]
+    SendMail(String),
+
     #[error("Unauthorized")]
     Unauthorized,
 
+    #[error("Invalid email: {0}")]
+    InvalidEmail(String),
+
+    #[error("Password must have {min} to {max} characters")]
+    InvalidPassword { min: usize, max: usize },
+
+    #[error("Email was registered")]
+    EmailRegistered,
+
+    #[error("Incorrect email or password")]
+    IncorrectCredentials,
+
+    /// Rejected by brute-force protection, with seconds until the next attempt
+    /// is allowed
+    #[error("Too many attempts, please retry after {0} seconds")]
+    TooManyAttempts(u64),
+
     #[error("{0}")]
     Custom(String),
 }
@@ -46,11 +111,18 @@ impl<'r, 'o: 'r> response::Responder<'r, 'o> for Error {
         use response::status::*;
         use response::*;
         match self {
-            Error::MalformedFeed(_) | Error::FetchFeedRequest(_) | Error::FetchFeedStatus(_) => {
+            Error::MalformedFeed(_) | Error::FetchFeedRequest(_) | Error::FetchFeedStatus(_) | Error::SendMail(_) => {
                 Custom(Status::FailedDependency, self.to_string()).respond_to(request)
             }
             Error::Database(e) => Debug(e).respond_to(request),
-            Error::Unauthorized => Unauthorized(Some(self.to_string())).respond_to(request),
+            Error::Unauthorized | Error::IncorrectCredentials => {
+                Unauthorized(Some(self.to_string())).respond_to(request)
+            }
+            Error::EmailRegistered => Conflict(Some(self.to_string())).respond_to(request),
+            Error::TooManyAttempts(retry_after) => Response::build_from(self.to_string().respond_to(request)?)
+                .status(Status::TooManyRequests)
+                .raw_header("Retry-After", retry_after.to_string())
+                .ok(),
             _ => BadRequest(Some(self.to_string())).respond_to(request),
         }
     }
@@ -1,4 +1,3 @@
-#![feature(iterator_try_collect)]
 #![feature(io_read_to_string)]
 
 #[macro_use]
@@ -6,11 +5,14 @@ extern crate rocket;
 
 use rocket::fairing::AdHoc;
 use rocket::fs::FileServer;
+use rocket::serde::Deserialize;
 use rocket::{fairing, Build, Rocket};
 use rocket_db_pools::{sqlx, Database};
 
 mod error;
+mod mailer;
 mod model;
+mod poller;
 mod route;
 mod util;
 
@@ -18,13 +20,33 @@ mod util;
 #[database("sqlx")]
 pub struct Db(sqlx::SqlitePool);
 
+/// Settings of the site in `Rocket.toml`, besides the ones of Rocket itself
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SiteConfig {
+    /// Public URL of the site, which links in merged feeds and mails point to
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+
+    /// Header with client IPs set by a reverse proxy in front of the site,
+    /// e.g. `X-Real-IP`. Only set it if clients can't reach the site directly.
+    pub proxy_ip_header: Option<String>,
+}
+
+fn default_base_url() -> String {
+    "http://localhost:8000".to_owned()
+}
+
 #[launch]
 fn rocket() -> _ {
     rocket::build()
         .attach(Db::init())
+        .attach(AdHoc::config::<SiteConfig>())
         .attach(AdHoc::try_on_ignite("Run Migrations", run_migrations))
         .mount("/", FileServer::from("./static"))
+        .attach(mailer::stage())
         .attach(route::stage())
+        .attach(poller::stage())
 }
 
 async fn run_migrations(rocket: Rocket<Build>) -> fairing::Result {
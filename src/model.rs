@@ -8,7 +8,22 @@ pub struct FeedItem {
     pub title: String,
     pub link: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub guid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub pub_date: Option<DateTime<Utc>>,
+    /// Summary of the item, may contain HTML
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Full content of the item (e.g. `<content:encoded>`), may contain HTML
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// Link to the comments page of the item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
 }
 
 /// Metadata of Feed
@@ -26,7 +41,15 @@ pub struct FeedInfo {
     pub items: Vec<FeedItem>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Format of the merged feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum OutputFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct SourceFeed {
     #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
@@ -34,6 +57,68 @@ pub struct SourceFeed {
     pub name: String,
     pub url: String,
     pub keywords: String,
+
+    /// Regular expression which titles of items must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+
+    /// Additional rules on other fields of items, all of which must pass
+    #[serde(default)]
+    pub rules: Vec<FilterRule>,
+
+    /// Matches keywords and regex case-insensitively
+    #[serde(default)]
+    pub ignore_case: bool,
+
+    /// Matches after Unicode NFKC normalization, e.g. `１０８０Ｐ` matches
+    /// `1080P`
+    #[serde(default)]
+    pub normalize: bool,
+
+    /// IANA name of the timezone of dates without offsets in the feed, e.g.
+    /// `Asia/Shanghai`. Defaults to UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+
+    /// Whether `keywords` were stored before keyword queries, and are matched
+    /// as plain words separated by spaces
+    #[serde(skip)]
+    pub legacy_keywords: bool,
+}
+
+/// Filter rule matching a keyword query against one field of items
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FilterRule {
+    pub field: FilterField,
+
+    /// Keyword query, e.g. `"1080p" (简体 OR 繁體)`
+    pub keywords: String,
+
+    /// Drops the matched items instead of keeping them
+    #[serde(default)]
+    pub exclude: bool,
+
+    /// Whether items lacking `field` pass the rule. Defaults to dropping them
+    /// for inclusive rules, and keeping them for exclusive ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_missing: Option<bool>,
+}
+
+/// Field of items targeted by a [`FilterRule`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum FilterField {
+    Title,
+
+    /// Summary or full content
+    Description,
+
+    /// Any of the categories (or tags)
+    Category,
+
+    /// `<author>` or `<dc:creator>`
+    Author,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -49,9 +134,86 @@ pub struct User {
     pub token: Option<String>,
 }
 
+/// Login session of a user on one device
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Session {
+    pub id: i64,
+
+    /// `User-Agent` of the login request
+    pub user_agent: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+
+    pub last_seen_at: DateTime<Utc>,
+
+    pub expires_at: DateTime<Utc>,
+
+    /// Whether it's the session of the request itself
+    pub current: bool,
+}
+
+/// Named merged feed of a subset of the feeds of a user, served at
+/// `/rss?token=` with its own token
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Collection {
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    pub name: String,
+
+    /// Description of the merged channel
+    #[serde(default)]
+    pub description: String,
+
+    /// Generated on creation
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// IDs of the source feeds
+    #[serde(default)]
+    pub feeds: Vec<i64>,
+}
+
+/// Preferences of a user on the merged feed
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserSettings {
+    /// Also treats items with the same title (ignoring case, width and
+    /// punctuation) as duplicates, besides the same guid or link
+    #[serde(default)]
+    pub dedup_titles: bool,
+
+    /// Prefixes titles of items with the name of their source feed
+    #[serde(default)]
+    pub prefix_titles: bool,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct LoginForm {
     pub email: String,
     pub password: String,
 }
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasswordChangeForm {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Asks for a password reset link to be mailed to `email`
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasswordResetForm {
+    /// Token of the mailed link
+    pub token: String,
+    pub new_password: String,
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use rocket::fairing::AdHoc;
+use rocket::serde::json::serde_json;
+use rocket::serde::Deserialize;
+use rocket::{futures, tokio};
+use rocket_db_pools::{sqlx, Database};
+use sqlx::types::Json;
+use sqlx::SqlitePool;
+
+use crate::error::{Error, Result};
+use crate::model::{FilterRule, SourceFeed};
+use crate::route::FEED_ITEMS_LIMIT;
+use crate::util::{fetch_feed_entries, http_client, CacheValidators, FeedEntry, FetchedFeed};
+use crate::Db;
+
+/// Interval of checking for feeds due to be polled
+const TICK: Duration = Duration::from_secs(60);
+
+/// Max number of feeds fetched at the same time
+const POLL_CONCURRENCY: usize = 8;
+
+/// Max number of items kept in the database per feed
+const ITEMS_RETAINED: i64 = 500;
+
+/// Configuration under `[default.poller]` of `Rocket.toml`
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct PollerConfig {
+    /// Seconds between two fetches of the same feed
+    interval: u64,
+}
+
+impl Default for PollerConfig {
+    fn default() -> Self {
+        Self { interval: 15 * 60 }
+    }
+}
+
+/// Spawns the task polling upstream feeds into the `items` table, from which
+/// the merged feed is served
+pub fn stage() -> AdHoc {
+    AdHoc::on_liftoff("Feed Poller", |rocket| {
+        Box::pin(async move {
+            let config = match rocket.figment().extract_inner::<PollerConfig>("poller") {
+                Ok(config) => config,
+                Err(e) if e.missing() => PollerConfig::default(),
+                Err(e) => {
+                    error!("Invalid poller config, falling back to default: {}", e);
+                    PollerConfig::default()
+                }
+            };
+            match Db::fetch(rocket) {
+                Some(db) => {
+                    tokio::spawn(run(db.0.clone(), config));
+                }
+                None => error!("Database is not initialized, feed poller is disabled"),
+            }
+        })
+    })
+}
+
+async fn run(pool: SqlitePool, config: PollerConfig) {
+    let mut ticker = tokio::time::interval(TICK);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = poll_due_feeds(&pool, &config).await {
+            error_!("Failed to poll feeds: {}", e);
+        }
+    }
+}
+
+/// Polls the feeds which have never been fetched or were fetched more than
+/// `config.interval` ago
+async fn poll_due_feeds(pool: &SqlitePool, config: &PollerConfig) -> Result<()> {
+    let modifier = format!("-{} seconds", config.interval);
+    let feeds = sqlx::query!(
+        "SELECT id, name, url, keywords, regex, rules AS \"rules: Json<Vec<FilterRule>>\", ignore_case, normalize, \
+        timezone, legacy_keywords, etag, last_modified \
+        FROM feeds \
+        WHERE fetched_at IS NULL OR fetched_at <= datetime('now', ?)",
+        modifier
+    )
+    .fetch(pool)
+    .map_ok(|r| {
+        let feed = SourceFeed {
+            id: Some(r.id),
+            name: r.name,
+            url: r.url,
+            keywords: r.keywords,
+            regex: r.regex,
+            rules: r.rules.0,
+            ignore_case: r.ignore_case,
+            normalize: r.normalize,
+            timezone: r.timezone,
+            legacy_keywords: r.legacy_keywords,
+        };
+        let validators = CacheValidators {
+            etag: r.etag,
+            last_modified: r.last_modified,
+        };
+        (feed, validators)
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+    if feeds.is_empty() {
+        return Ok(());
+    }
+
+    let client = http_client()?;
+    let start_time = Instant::now();
+    stream::iter(&feeds)
+        .for_each_concurrent(POLL_CONCURRENCY, |(feed, validators)| {
+            poll_feed(pool, &client, feed, validators)
+        })
+        .await;
+    info_!("Polling {} feeds costs {:?}", feeds.len(), start_time.elapsed());
+    Ok(())
+}
+
+/// Fetches `feed` and stores its entries. Failures are recorded in
+/// `feeds.fetch_error` so that they can be reported with the merged feed.
+async fn poll_feed(pool: &SqlitePool, client: &reqwest::Client, feed: &SourceFeed, validators: &CacheValidators) {
+    let id = feed.id.unwrap_or_default();
+    let result = match fetch_feed_entries(client, feed, validators, FEED_ITEMS_LIMIT).await {
+        Ok(FetchedFeed::Modified { entries, validators }) => save_entries(pool, id, entries, &validators).await,
+        Ok(FetchedFeed::NotModified) => Ok(()),
+        Err(e) => Err(e),
+    };
+    let fetch_error = result.err().map(|e| {
+        warn_!("Failed to poll feed {} ({}): {}", feed.name, feed.url, e);
+        e.to_string()
+    });
+
+    let result = sqlx::query!(
+        "UPDATE feeds SET fetched_at = CURRENT_TIMESTAMP, fetch_error = ? WHERE id = ?",
+        fetch_error,
+        id
+    )
+    .execute(pool)
+    .await;
+    if let Err(e) = result {
+        error_!("Failed to update feed {}: {}", id, e);
+    }
+}
+
+/// Upserts `entries` of the feed keyed by their guid (or link), and drops the
+/// oldest items beyond [`ITEMS_RETAINED`]. `validators` of the response are
+/// saved for the next conditional fetch.
+async fn save_entries(
+    pool: &SqlitePool,
+    feed_id: i64,
+    entries: Vec<FeedEntry>,
+    validators: &CacheValidators,
+) -> Result<()> {
+    let entries: Vec<_> = entries
+        .into_iter()
+        .map(|entry| (entry.guid.clone().unwrap_or_else(|| entry.link.clone()), entry))
+        .collect();
+    // Entries without a (parsable) date are dated when first seen, instead of
+    // sinking to the bottom of the merged feed. They are looked up before the
+    // transaction, which can't upgrade a read to a write once other pollers
+    // have written in between.
+    let undated_guids: Vec<&String> = entries
+        .iter()
+        .filter(|(_, entry)| entry.pub_date.is_none())
+        .map(|(guid, _)| guid)
+        .collect();
+    let first_seen: HashMap<String, DateTime<Utc>> = sqlx::query!(
+        "SELECT guid, pub_date AS \"pub_date: DateTime<Utc>\" FROM items \
+        WHERE feed_id = ? AND guid IN (SELECT value FROM json_each(?))",
+        feed_id,
+        Json(&undated_guids)
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|r| Some((r.guid, r.pub_date?)))
+    .collect();
+
+    let mut tx = pool.begin().await?;
+    for (guid, mut entry) in entries {
+        if entry.pub_date.is_none() {
+            entry.pub_date = Some(first_seen.get(&guid).copied().unwrap_or_else(Utc::now));
+        }
+        let data = serde_json::to_string(&entry).map_err(|e| Error::Custom(e.to_string()))?;
+        sqlx::query!(
+            "INSERT INTO items (feed_id, guid, pub_date, data) VALUES (?, ?, ?, ?) \
+            ON CONFLICT (feed_id, guid) DO UPDATE SET pub_date = excluded.pub_date, data = excluded.data \
+            WHERE data != excluded.data",
+            feed_id,
+            guid,
+            entry.pub_date,
+            data
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        "DELETE FROM items WHERE feed_id = ? AND id NOT IN \
+        (SELECT id FROM items WHERE feed_id = ? ORDER BY pub_date DESC, id DESC LIMIT ?)",
+        feed_id,
+        feed_id,
+        ITEMS_RETAINED
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE feeds SET etag = ?, last_modified = ? WHERE id = ?",
+        validators.etag,
+        validators.last_modified,
+        feed_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use rocket::http::Status;
+
+    use super::*;
+    use crate::route::tests::TestClient;
+
+    async fn insert_feed(client: &TestClient, url: &str) -> i64 {
+        sqlx::query("INSERT INTO feeds (name, url, keywords, user_id) VALUES ('Anime', ?, '', ?)")
+            .bind(url)
+            .bind(client.user_id)
+            .execute(&client.db)
+            .await
+            .unwrap()
+            .last_insert_rowid()
+    }
+
+    fn entry(guid: &str, pub_date: Option<DateTime<Utc>>) -> FeedEntry {
+        FeedEntry {
+            title: guid.to_owned(),
+            link: format!("https://example.com/{guid}"),
+            guid: Some(guid.to_owned()),
+            pub_date,
+            ..Default::default()
+        }
+    }
+
+    /// Undated entries keep the date when they were first seen, and items are
+    /// left untouched while their entries don't change
+    #[rocket::async_test]
+    async fn test_save_undated_entries() {
+        let client = TestClient::new().await;
+        let feed_id = insert_feed(&client, "https://example.com/rss").await;
+        let first_seen = Utc::now() - Duration::days(1);
+        sqlx::query(
+            "INSERT INTO items (feed_id, guid, pub_date, data, updated_at) \
+            VALUES (?, 'a', ?, ?, '2022-01-01 00:00:00')",
+        )
+        .bind(feed_id)
+        .bind(first_seen)
+        .bind(serde_json::to_string(&entry("a", Some(first_seen))).unwrap())
+        .execute(&client.db)
+        .await
+        .unwrap();
+        let stored = |guid: &'static str| {
+            sqlx::query_as::<_, (DateTime<Utc>, String)>(
+                "SELECT pub_date, updated_at FROM items WHERE feed_id = ? AND guid = ?",
+            )
+            .bind(feed_id)
+            .bind(guid)
+            .fetch_one(&client.db)
+        };
+
+        let entries = vec![entry("a", None), entry("b", None)];
+        save_entries(&client.db, feed_id, entries, &CacheValidators::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            stored("a").await.unwrap(),
+            (first_seen, "2022-01-01 00:00:00".to_owned())
+        );
+        let (pub_date, _) = stored("b").await.unwrap();
+        assert!(pub_date > first_seen && pub_date <= Utc::now());
+    }
+
+    /// Items beyond [`ITEMS_RETAINED`] are dropped oldest first
+    #[rocket::async_test]
+    async fn test_prune_items() {
+        let client = TestClient::new().await;
+        let feed_id = insert_feed(&client, "https://example.com/rss").await;
+        let start = Utc::now() - Duration::days(1);
+        let entries = (0..ITEMS_RETAINED)
+            .map(|i| entry(&i.to_string(), Some(start + Duration::minutes(i))))
+            .collect();
+        save_entries(&client.db, feed_id, entries, &CacheValidators::default())
+            .await
+            .unwrap();
+
+        let entries = vec![entry("new", Some(Utc::now()))];
+        let validators = CacheValidators {
+            etag: Some("\"v2\"".to_owned()),
+            last_modified: None,
+        };
+        save_entries(&client.db, feed_id, entries, &validators).await.unwrap();
+        let guids = sqlx::query_scalar::<_, String>("SELECT guid FROM items WHERE feed_id = ?")
+            .bind(feed_id)
+            .fetch_all(&client.db)
+            .await
+            .unwrap();
+        assert_eq!(guids.len(), ITEMS_RETAINED as usize);
+        assert!(!guids.contains(&"0".to_owned()));
+        assert!(guids.contains(&"1".to_owned()) && guids.contains(&"new".to_owned()));
+        let etag = sqlx::query_scalar::<_, Option<String>>("SELECT etag FROM feeds WHERE id = ?")
+            .bind(feed_id)
+            .fetch_one(&client.db)
+            .await
+            .unwrap();
+        assert_eq!(etag.as_deref(), Some("\"v2\""));
+    }
+
+    /// Items stay in the merged feed after they scroll out of the upstream feed
+    #[rocket::async_test]
+    async fn test_serve_scrolled_out_items() {
+        let client = TestClient::new().await;
+        let feed_id = insert_feed(&client, "https://example.com/rss").await;
+        let start = Utc::now() - Duration::days(1);
+        for guids in [["a", "b"], ["b", "c"]] {
+            let entries = guids
+                .iter()
+                .enumerate()
+                .map(|(i, guid)| entry(guid, Some(start + Duration::hours(i as i64))))
+                .collect();
+            save_entries(&client.db, feed_id, entries, &CacheValidators::default())
+                .await
+                .unwrap();
+        }
+
+        let uri = format!("/rss?token={}", client.token);
+        let resp = client.client.get(uri).dispatch().await;
+        assert_eq!(resp.status(), Status::Ok);
+        let body = resp.into_string().await.unwrap();
+        for guid in ["a", "b", "c"] {
+            assert!(
+                body.contains(&format!("https://example.com/{guid}")),
+                "missing item {guid}"
+            );
+        }
+    }
+
+    /// Failed fetches are recorded and reported with the merged feed
+    #[rocket::async_test]
+    async fn test_poll_failed_feed() {
+        let client = TestClient::new().await;
+        let feed = SourceFeed {
+            id: Some(insert_feed(&client, "http://127.0.0.1:1/rss").await),
+            name: "Anime".to_owned(),
+            url: "http://127.0.0.1:1/rss".to_owned(),
+            ..Default::default()
+        };
+        poll_feed(&client.db, &http_client().unwrap(), &feed, &CacheValidators::default()).await;
+        let (fetch_error, fetched) = sqlx::query_as::<_, (Option<String>, bool)>(
+            "SELECT fetch_error, fetched_at IS NOT NULL FROM feeds WHERE id = ?",
+        )
+        .bind(feed.id)
+        .fetch_one(&client.db)
+        .await
+        .unwrap();
+        assert!(fetch_error.is_some() && fetched);
+
+        let uri = format!("/rss?token={}", client.token);
+        let resp = client.client.get(uri).dispatch().await;
+        assert_eq!(resp.status(), Status::Ok);
+        let expected = feed.id.unwrap().to_string();
+        assert_eq!(resp.headers().get_one("X-Mior-Failed-Feeds"), Some(expected.as_str()));
+    }
+}
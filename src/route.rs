@@ -1,24 +1,62 @@
+use std::net::{IpAddr, Ipv6Addr};
+
+use chrono::{DateTime, TimeZone, Utc};
 use futures::stream::TryStreamExt;
+use reqwest::Url;
 use rocket::fairing::AdHoc;
-use rocket::http::{ContentType, Cookie, CookieJar};
+use rocket::http::{ContentType, Cookie, CookieJar, Status};
 use rocket::request::FromRequest;
 use rocket::response::status::{Created, NoContent};
+use rocket::response::{self, Responder, Response};
 use rocket::serde::json::{serde_json, Json};
-use rocket::{futures, request, Request};
-use rocket_db_pools::{sqlx, Connection};
-use sqlx::error::DatabaseError;
-use sqlx::sqlite::SqliteError;
+use rocket::serde::Serialize;
+use rocket::time::{self, OffsetDateTime};
+use rocket::{futures, request, Request, State};
+use rocket_db_pools::{sqlx, Connection, Database as _};
+use sqlx::{Connection as _, SqlitePool};
 
 use crate::error::{Error, Result};
-use crate::model::{FeedInfo, LoginForm, SourceFeed, User};
-use crate::util::{fetch_rss_info, merge_feeds_data};
-use crate::Db;
+use crate::mailer::{Mail, Mailer};
+use crate::model::{
+    Collection, FeedInfo, FilterRule, LoginForm, OutputFormat, PasswordChangeForm, PasswordResetForm,
+    PasswordResetRequest, Session, SourceFeed, User, UserSettings,
+};
+use crate::util::{
+    feed_timezone, fetch_rss_info, hash_password, validate_email, validate_password, verify_password, EntrySource,
+    FeedEntry, FeedFilter, FeedMerger, PasswordMatch, RateLimiter, DUMMY_HASH,
+};
+use crate::{Db, SiteConfig};
+
+/// Extended result code of SQLite for violations of unique constraints
+const SQLITE_CONSTRAINT_UNIQUE: &str = "2067";
 
 /// Default limit of feeds per user
 const FEEDS_LIMIT: usize = 20;
 
+/// Default limit of collections per user
+const COLLECTIONS_LIMIT: usize = 10;
+
+/// Days for which a login session is valid
+const SESSION_LIFETIME_DAYS: i64 = 30;
+
+/// Max hours for which a rotated token of the merged feed keeps working
+const MAX_TOKEN_GRACE_HOURS: u32 = 7 * 24;
+
+/// Minutes for which a mailed password reset link is valid
+const PASSWORD_RESET_LIFETIME_MINUTES: i64 = 60;
+
+/// Private cookie holding the token of the login session
+const SESSION_COOKIE: &str = "session";
+
 /// Default limit of items per feed
-const FEED_ITEMS_LIMIT: usize = 100;
+pub(crate) const FEED_ITEMS_LIMIT: usize = 100;
+
+/// Default limit of items in the merged feed, the most recent ones. Older
+/// items stay stored until they are pruned by the poller.
+const MERGED_ITEMS_LIMIT: i64 = FEED_ITEMS_LIMIT as i64;
+
+/// Seconds for which clients may reuse the merged feed without revalidation
+const MERGED_FEED_MAX_AGE: u32 = 5 * 60;
 
 #[post("/", data = "<feed>")]
 async fn create(mut db: Connection<Db>, user: User, feed: Json<SourceFeed>) -> Result<Created<()>> {
@@ -31,12 +69,22 @@ async fn create(mut db: Connection<Db>, user: User, feed: Json<SourceFeed>) -> R
             "Number of feeds reached limit ({feed_count}/{FEEDS_LIMIT})"
         )));
     }
+    // Reject invalid filters here instead of failing every poll of the feed
+    FeedFilter::new(&feed)?;
+    feed_timezone(&feed)?;
 
+    let rules = sqlx::types::Json(&feed.rules);
     sqlx::query!(
-        "INSERT INTO feeds (name, url, keywords, user_id) VALUES (?, ?, ?, ?)",
+        "INSERT INTO feeds (name, url, keywords, regex, rules, ignore_case, normalize, timezone, user_id) \
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
         feed.name,
         feed.url,
         feed.keywords,
+        feed.regex,
+        rules,
+        feed.ignore_case,
+        feed.normalize,
+        feed.timezone,
         user.id,
     )
     .execute(&mut *db)
@@ -47,16 +95,27 @@ async fn create(mut db: Connection<Db>, user: User, feed: Json<SourceFeed>) -> R
 
 #[get("/")]
 async fn list(mut db: Connection<Db>, user: User) -> Result<Json<Vec<SourceFeed>>> {
-    let feeds = sqlx::query!("SELECT id, name, url, keywords FROM feeds WHERE user_id = ?", user.id)
-        .fetch(&mut *db)
-        .map_ok(|r| SourceFeed {
-            id: Some(r.id),
-            name: r.name,
-            url: r.url,
-            keywords: r.keywords,
-        })
-        .try_collect::<Vec<_>>()
-        .await?;
+    let feeds = sqlx::query!(
+        "SELECT id, name, url, keywords, regex, rules AS \"rules: sqlx::types::Json<Vec<FilterRule>>\", \
+        ignore_case, normalize, timezone, legacy_keywords \
+        FROM feeds WHERE user_id = ?",
+        user.id
+    )
+    .fetch(&mut *db)
+    .map_ok(|r| SourceFeed {
+        id: Some(r.id),
+        name: r.name,
+        url: r.url,
+        keywords: r.keywords,
+        regex: r.regex,
+        rules: r.rules.0,
+        ignore_case: r.ignore_case,
+        normalize: r.normalize,
+        timezone: r.timezone,
+        legacy_keywords: r.legacy_keywords,
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
 
     Ok(Json(feeds))
 }
@@ -75,37 +134,406 @@ async fn delete(mut db: Connection<Db>, user: User, id: i64) -> Result<NoContent
 }
 
 #[post("/<id>", data = "<feed>")]
-async fn update(mut db: Connection<Db>, user: User, id: i64, feed: Json<SourceFeed>) -> Result<NoContent> {
+async fn update(mut db: Connection<Db>, user: User, id: i64, mut feed: Json<SourceFeed>) -> Result<NoContent> {
+    keep_legacy_keywords(&mut db, &user, id, &mut feed).await?;
+    FeedFilter::new(&feed)?;
+    feed_timezone(&feed)?;
+
+    let rules = sqlx::types::Json(&feed.rules);
+    let mut tx = db.begin().await?;
+    // Reset the polling state if the stored items may not match the new URL,
+    // filters or timezone, while renames keep them
+    let reset = sqlx::query!(
+        "UPDATE feeds SET fetched_at = NULL, fetch_error = NULL, etag = NULL, last_modified = NULL \
+        WHERE id = ?9 AND user_id = ?10 AND (url != ?1 OR keywords != ?2 OR legacy_keywords != ?3 \
+        OR regex IS NOT ?4 OR rules != ?5 OR ignore_case != ?6 OR normalize != ?7 OR timezone IS NOT ?8)",
+        feed.url,
+        feed.keywords,
+        feed.legacy_keywords,
+        feed.regex,
+        rules,
+        feed.ignore_case,
+        feed.normalize,
+        feed.timezone,
+        id,
+        user.id,
+    )
+    .execute(&mut tx)
+    .await?;
     let result = sqlx::query!(
-        "UPDATE feeds SET name = ?, url = ?, keywords = ? WHERE id = ? AND user_id = ?",
+        "UPDATE feeds SET name = ?1, url = ?2, keywords = ?3, legacy_keywords = ?4, \
+        regex = ?5, rules = ?6, ignore_case = ?7, normalize = ?8, timezone = ?9 \
+        WHERE id = ?10 AND user_id = ?11",
         feed.name,
         feed.url,
         feed.keywords,
+        feed.legacy_keywords,
+        feed.regex,
+        rules,
+        feed.ignore_case,
+        feed.normalize,
+        feed.timezone,
         id,
         user.id,
     )
+    .execute(&mut tx)
+    .await?;
+    if result.rows_affected() != 1 {
+        return Err(Error::FeedNotFound(id));
+    }
+
+    if reset.rows_affected() == 1 {
+        sqlx::query!("DELETE FROM items WHERE feed_id = ?", id)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(NoContent)
+}
+
+/// Keeps the keywords of the stored feed `id` matched as plain words if they
+/// were stored before keyword queries, until they are edited in `feed`
+async fn keep_legacy_keywords(db: &mut Connection<Db>, user: &User, id: i64, feed: &mut SourceFeed) -> Result<()> {
+    let stored = sqlx::query!(
+        "SELECT keywords, legacy_keywords FROM feeds WHERE id = ? AND user_id = ?",
+        id,
+        user.id
+    )
+    .fetch_optional(&mut **db)
+    .await?
+    .ok_or(Error::FeedNotFound(id))?;
+
+    feed.legacy_keywords = stored.legacy_keywords && stored.keywords == feed.keywords;
+    Ok(())
+}
+
+/// Previews the items of `feed` which pass its filter. `id` is of the stored
+/// feed being edited, if any, so that its items are filtered as polled.
+#[post("/fetch?<id>", data = "<feed>")]
+async fn fetch(
+    mut db: Connection<Db>,
+    user: Option<User>,
+    id: Option<i64>,
+    mut feed: Json<SourceFeed>,
+) -> Result<Json<FeedInfo>> {
+    if let (Some(user), Some(id)) = (user, id) {
+        keep_legacy_keywords(&mut db, &user, id, &mut feed).await?;
+    }
+    fetch_rss_info(&feed, FEED_ITEMS_LIMIT).await.map(Json)
+}
+
+/// Previews all the items of `url`, for clients of the API from before
+/// filters could be previewed
+#[get("/fetch?<url>")]
+async fn fetch_url(url: &str) -> Result<Json<FeedInfo>> {
+    let feed = SourceFeed {
+        url: url.to_owned(),
+        ..Default::default()
+    };
+    fetch_rss_info(&feed, FEED_ITEMS_LIMIT).await.map(Json)
+}
+
+#[get("/")]
+async fn list_collections(mut db: Connection<Db>, user: User) -> Result<Json<Vec<Collection>>> {
+    let mut collections = sqlx::query!(
+        "SELECT id, name, description, token FROM collections WHERE user_id = ?",
+        user.id
+    )
+    .fetch(&mut *db)
+    .map_ok(|r| Collection {
+        id: Some(r.id),
+        name: r.name,
+        description: r.description,
+        token: Some(r.token),
+        feeds: Vec::new(),
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    let mut collection_feeds = sqlx::query!(
+        "SELECT collection_feeds.collection_id AS collection_id, collection_feeds.feed_id AS feed_id \
+        FROM collection_feeds, collections \
+        WHERE collection_feeds.collection_id = collections.id AND collections.user_id = ? \
+        ORDER BY collection_feeds.feed_id",
+        user.id
+    )
+    .fetch(&mut *db);
+    while let Some(r) = collection_feeds.try_next().await? {
+        if let Some(collection) = collections.iter_mut().find(|c| c.id == Some(r.collection_id)) {
+            collection.feeds.push(r.feed_id);
+        }
+    }
+
+    Ok(Json(collections))
+}
+
+#[post("/", data = "<collection>")]
+async fn create_collection(
+    mut db: Connection<Db>,
+    user: User,
+    mut collection: Json<Collection>,
+) -> Result<Created<()>> {
+    let collection_count = sqlx::query!(
+        "SELECT count(*) AS collection_count FROM collections WHERE user_id = ?",
+        user.id
+    )
+    .fetch_one(&mut *db)
+    .await
+    .map(|r| r.collection_count as usize)?;
+    if collection_count >= COLLECTIONS_LIMIT {
+        return Err(Error::Custom(format!(
+            "Number of collections reached limit ({collection_count}/{COLLECTIONS_LIMIT})"
+        )));
+    }
+    // Feeds listed more than once are added once
+    collection.feeds.sort_unstable();
+    collection.feeds.dedup();
+    check_feeds_owned(&mut db, &user, &collection.feeds).await?;
+
+    let token = crate::util::gen_rand_token();
+    let mut tx = db.begin().await?;
+    let id = sqlx::query!(
+        "INSERT INTO collections (user_id, name, description, token) VALUES (?, ?, ?, ?) RETURNING id",
+        user.id,
+        collection.name,
+        collection.description,
+        token
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .id;
+    for feed_id in &collection.feeds {
+        sqlx::query!(
+            "INSERT INTO collection_feeds (collection_id, feed_id) VALUES (?, ?)",
+            id,
+            feed_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Created::new("/").body(()))
+}
+
+/// Replaces the token of the merged feed of a collection, with the same grace
+/// period as [`rotate_token`]
+#[post("/<id>/token?<grace_hours>")]
+async fn rotate_collection_token(
+    mut db: Connection<Db>,
+    user: User,
+    id: i64,
+    grace_hours: Option<u32>,
+) -> Result<NoContent> {
+    let grace = token_grace(grace_hours)?;
+    let token = crate::util::gen_rand_token();
+    let result = sqlx::query!(
+        "UPDATE collections SET token = ?1, previous_token = CASE WHEN ?2 IS NULL THEN NULL ELSE token END, \
+        previous_token_expires_at = datetime('now', ?2) \
+        WHERE id = ?3 AND user_id = ?4",
+        token,
+        grace,
+        id,
+        user.id
+    )
     .execute(&mut *db)
     .await?;
 
     if result.rows_affected() == 1 {
         Ok(NoContent)
     } else {
-        Err(Error::FeedNotFound(id))
+        Err(Error::CollectionNotFound(id))
     }
 }
 
-#[get("/fetch?<url>")]
-async fn fetch(url: &str) -> Result<Json<FeedInfo>> {
-    fetch_rss_info(url, FEED_ITEMS_LIMIT).await.map(Json)
+/// Stops the previous token of a collection from working before its grace
+/// period ends
+#[delete("/<id>/token/previous")]
+async fn revoke_previous_collection_token(mut db: Connection<Db>, user: User, id: i64) -> Result<NoContent> {
+    let result = sqlx::query!(
+        "UPDATE collections SET previous_token = NULL, previous_token_expires_at = NULL \
+        WHERE id = ? AND user_id = ?",
+        id,
+        user.id
+    )
+    .execute(&mut *db)
+    .await?;
+
+    if result.rows_affected() == 1 {
+        Ok(NoContent)
+    } else {
+        Err(Error::CollectionNotFound(id))
+    }
+}
+
+#[delete("/<id>")]
+async fn delete_collection(mut db: Connection<Db>, user: User, id: i64) -> Result<NoContent> {
+    let result = sqlx::query!("DELETE FROM collections WHERE id = ? AND user_id = ?", id, user.id)
+        .execute(&mut *db)
+        .await?;
+
+    if result.rows_affected() == 1 {
+        Ok(NoContent)
+    } else {
+        Err(Error::CollectionNotFound(id))
+    }
+}
+
+/// Updates the name, description and feeds of a collection, while its token
+/// is kept
+#[post("/<id>", data = "<collection>")]
+async fn update_collection(
+    mut db: Connection<Db>,
+    user: User,
+    id: i64,
+    mut collection: Json<Collection>,
+) -> Result<NoContent> {
+    collection.feeds.sort_unstable();
+    collection.feeds.dedup();
+    check_feeds_owned(&mut db, &user, &collection.feeds).await?;
+
+    let mut tx = db.begin().await?;
+    let result = sqlx::query!(
+        "UPDATE collections SET name = ?, description = ? WHERE id = ? AND user_id = ?",
+        collection.name,
+        collection.description,
+        id,
+        user.id
+    )
+    .execute(&mut tx)
+    .await?;
+    if result.rows_affected() != 1 {
+        return Err(Error::CollectionNotFound(id));
+    }
+
+    sqlx::query!("DELETE FROM collection_feeds WHERE collection_id = ?", id)
+        .execute(&mut tx)
+        .await?;
+    for feed_id in &collection.feeds {
+        sqlx::query!(
+            "INSERT INTO collection_feeds (collection_id, feed_id) VALUES (?, ?)",
+            id,
+            feed_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(NoContent)
+}
+
+/// Rejects feeds of other users from being added into a collection
+async fn check_feeds_owned(db: &mut Connection<Db>, user: &User, feeds: &[i64]) -> Result<()> {
+    let owned_feeds = sqlx::query!("SELECT id FROM feeds WHERE user_id = ?", user.id)
+        .fetch(&mut **db)
+        .map_ok(|r| r.id)
+        .try_collect::<Vec<_>>()
+        .await?;
+    match feeds.iter().find(|id| !owned_feeds.contains(id)) {
+        Some(id) => Err(Error::FeedNotFound(*id)),
+        None => Ok(()),
+    }
+}
+
+/// Brute-force protection of `login`, `register` and passwords
+struct AuthLimiters {
+    /// Failed logins of each client IP
+    login_ip: RateLimiter,
+
+    /// Failed logins of each email, from whichever IPs
+    login_email: RateLimiter,
+
+    /// Registrations of each client IP, counted whether they succeed or not
+    register_ip: RateLimiter,
+
+    /// Password reset requests of each client IP
+    reset_ip: RateLimiter,
+
+    /// Password reset requests of each email, whether it's registered or not
+    reset_email: RateLimiter,
+
+    /// Incorrect current passwords of each user changing the password
+    password_user: RateLimiter,
+}
+
+impl Default for AuthLimiters {
+    fn default() -> Self {
+        Self {
+            login_ip: RateLimiter::new(20, std::time::Duration::from_secs(60)),
+            login_email: RateLimiter::new(5, std::time::Duration::from_secs(30)),
+            register_ip: RateLimiter::new(5, std::time::Duration::from_secs(10 * 60)),
+            reset_ip: RateLimiter::new(5, std::time::Duration::from_secs(10 * 60)),
+            reset_email: RateLimiter::new(3, std::time::Duration::from_secs(15 * 60)),
+            password_user: RateLimiter::new(5, std::time::Duration::from_secs(30)),
+        }
+    }
+}
+
+impl AuthLimiters {
+    fn check_login(&self, client_ip: &ClientIp, email: &str) -> Result<()> {
+        if let Some(ip) = &client_ip.0 {
+            self.login_ip.check(ip)?;
+        }
+        self.login_email.check(&email.to_lowercase())
+    }
+
+    fn record_login_failure(&self, client_ip: &ClientIp, email: &str) {
+        if let Some(ip) = &client_ip.0 {
+            self.login_ip.record_failure(ip);
+        }
+        self.login_email.record_failure(&email.to_lowercase());
+    }
+
+    /// Failures of the IP are kept, since it may be trying other emails
+    fn reset_login(&self, email: &str) {
+        self.login_email.reset(&email.to_lowercase());
+    }
+
+    fn check_register(&self, client_ip: &ClientIp) -> Result<()> {
+        match &client_ip.0 {
+            Some(ip) => self.register_ip.check(ip),
+            None => Ok(()),
+        }
+    }
+
+    fn record_register(&self, client_ip: &ClientIp) {
+        if let Some(ip) = &client_ip.0 {
+            self.register_ip.record_failure(ip);
+        }
+    }
+
+    /// Checks and counts a password reset request
+    fn record_reset(&self, client_ip: &ClientIp, email: &str) -> Result<()> {
+        let email = email.to_lowercase();
+        if let Some(ip) = &client_ip.0 {
+            self.reset_ip.check(ip)?;
+        }
+        self.reset_email.check(&email)?;
+        if let Some(ip) = &client_ip.0 {
+            self.reset_ip.record_failure(ip);
+        }
+        self.reset_email.record_failure(&email);
+        Ok(())
+    }
 }
 
 #[post("/register", data = "<user>")]
-async fn register(mut db: Connection<Db>, user: Json<User>, cookie: &CookieJar<'_>) -> Result<Json<User>> {
-    let password = user
-        .password
-        .as_ref()
-        .ok_or_else(|| Error::Custom("Password is None".to_owned()))?;
-    let hashed_password = hash_password(password);
+async fn register(
+    mut db: Connection<Db>,
+    user: Json<User>,
+    client_ip: ClientIp,
+    limiters: &State<AuthLimiters>,
+    ua: UserAgent<'_>,
+    cookie: &CookieJar<'_>,
+) -> Result<Json<User>> {
+    limiters.check_register(&client_ip)?;
+    validate_email(&user.email)?;
+    let password = user.password.as_deref().unwrap_or_default();
+    validate_password(password)?;
+    limiters.record_register(&client_ip);
+    let hashed_password = hash_password(password).await?;
 
     let token = crate::util::gen_rand_token();
 
@@ -126,67 +554,162 @@ async fn register(mut db: Connection<Db>, user: Json<User>, cookie: &CookieJar<'
         password: None,
         token: Some(r.token),
     })
-    .map_err(|e| {
-        if let sqlx::Error::Database(ref err) = e {
-            let err = err.downcast_ref::<SqliteError>();
-            if err.code().unwrap() == "2067" {
-                return Error::Custom("Email was registered".to_owned());
-            }
+    .map_err(|e| match e {
+        // Violation of the unique index on emails
+        sqlx::Error::Database(ref err)
+            if err.code().as_deref() == Some(SQLITE_CONSTRAINT_UNIQUE) && err.message().contains("users.email") =>
+        {
+            Error::EmailRegistered
         }
-        e.into()
+        _ => e.into(),
     })?;
 
-    cookie.add_private(Cookie::new("user", serde_json::to_string(&user).unwrap()));
+    start_session(&mut db, &user, ua, cookie).await?;
     Ok(Json(user))
 }
 
 #[post("/login", data = "<user>")]
-async fn login(mut db: Connection<Db>, user: Json<LoginForm>, cookie: &CookieJar<'_>) -> Result<Json<User>> {
-    let password = hash_password(&user.password);
-    let user = sqlx::query!(
-        "SELECT id, email, nickname, token FROM users WHERE email = ? AND password = ?",
-        user.email,
-        password
+async fn login(
+    mut db: Connection<Db>,
+    user: Json<LoginForm>,
+    client_ip: ClientIp,
+    limiters: &State<AuthLimiters>,
+    ua: UserAgent<'_>,
+    cookie: &CookieJar<'_>,
+) -> Result<Json<User>> {
+    limiters.check_login(&client_ip, &user.email)?;
+    // Legacy hashes were stored as BLOBs
+    let r = sqlx::query!(
+        r#"SELECT id, email, nickname, token, password AS "password: Vec<u8>" FROM users WHERE email = ?"#,
+        user.email
     )
-    .fetch_one(&mut *db)
-    .await
-    .map(|r| User {
+    .fetch_optional(&mut *db)
+    .await?;
+    let password_match = match &r {
+        Some(r) => verify_password(&user.password, &r.password).await?,
+        None => {
+            // Takes as long as a wrong password of a registered email
+            verify_password(&user.password, DUMMY_HASH.as_bytes()).await?;
+            PasswordMatch::Mismatch
+        }
+    };
+    let r = match r {
+        Some(r) if password_match != PasswordMatch::Mismatch => r,
+        _ => {
+            limiters.record_login_failure(&client_ip, &user.email);
+            return Err(Error::IncorrectCredentials);
+        }
+    };
+    limiters.reset_login(&user.email);
+    if password_match == PasswordMatch::Legacy {
+        let hashed_password = hash_password(&user.password).await?;
+        sqlx::query!("UPDATE users SET password = ? WHERE id = ?", hashed_password, r.id)
+            .execute(&mut *db)
+            .await?;
+    }
+    let user = User {
         id: Some(r.id),
         nickname: r.nickname,
         email: r.email,
         token: Some(r.token),
         password: None,
-    })
-    .map_err(|e| match e {
-        sqlx::Error::RowNotFound => Error::Custom("Incorrect user or password".to_owned()),
-        _ => e.into(),
-    })?;
+    };
 
-    cookie.add_private(Cookie::new("user", serde_json::to_string(&user).unwrap()));
+    start_session(&mut db, &user, ua, cookie).await?;
     Ok(Json(user))
 }
 
-const SALT: &str = "merge into one rss!";
+/// Creates a login session for `user` and hands its token to the client in
+/// a private cookie. Expired sessions of all users are purged meanwhile.
+async fn start_session(db: &mut Connection<Db>, user: &User, ua: UserAgent<'_>, cookie: &CookieJar<'_>) -> Result<()> {
+    sqlx::query!("DELETE FROM sessions WHERE expires_at <= datetime('now')")
+        .execute(&mut **db)
+        .await?;
 
-fn hash_password(password: &str) -> Vec<u8> {
-    use sha2::Digest;
-    let mut hasher = sha2::Sha256::new();
-    hasher.update(password.as_bytes());
-    hasher.update(SALT.as_bytes());
-    hasher.finalize().to_vec()
+    let token = crate::util::gen_rand_token();
+    let lifetime = format!("+{SESSION_LIFETIME_DAYS} days");
+    sqlx::query!(
+        "INSERT INTO sessions (user_id, token, user_agent, expires_at) VALUES (?, ?, ?, datetime('now', ?))",
+        user.id,
+        token,
+        ua.0,
+        lifetime
+    )
+    .execute(&mut **db)
+    .await?;
+
+    let mut session_cookie = Cookie::new(SESSION_COOKIE, token);
+    session_cookie.set_expires(OffsetDateTime::now_utc() + time::Duration::days(SESSION_LIFETIME_DAYS));
+    cookie.add_private(session_cookie);
+    Ok(())
+}
+
+/// Login session of the request. It's looked up in the database on every
+/// request, so that sessions revoked, expired or of deleted users no longer
+/// pass.
+struct UserSession {
+    id: i64,
+    user: User,
+}
+
+impl UserSession {
+    async fn find(db: &Db, token: &str) -> Result<Option<UserSession>> {
+        let session = sqlx::query!(
+            "SELECT sessions.id, users.id AS user_id, users.email, users.nickname, users.token \
+            FROM sessions, users \
+            WHERE sessions.user_id = users.id AND sessions.token = ? AND sessions.expires_at > datetime('now')",
+            token
+        )
+        .fetch_optional(&**db)
+        .await?
+        .map(|r| UserSession {
+            id: r.id,
+            user: User {
+                id: Some(r.user_id),
+                nickname: r.nickname,
+                email: r.email,
+                password: None,
+                token: Some(r.token),
+            },
+        });
+
+        if let Some(session) = &session {
+            sqlx::query!(
+                "UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP WHERE id = ?",
+                session.id
+            )
+            .execute(&**db)
+            .await?;
+        }
+        Ok(session)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserSession {
+    type Error = Error;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<UserSession, Self::Error> {
+        use rocket::outcome::Outcome::{Failure, Forward};
+        let token = match request.cookies().get_private(SESSION_COOKIE) {
+            Some(cookie) => cookie.value().to_owned(),
+            None => return Forward(()),
+        };
+        let db = Db::fetch(request.rocket()).expect("database is attached");
+        match UserSession::find(db, &token).await {
+            Ok(Some(session)) => Success(session),
+            Ok(None) => Forward(()),
+            Err(e) => Failure((Status::InternalServerError, e)),
+        }
+    }
 }
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for User {
-    type Error = std::convert::Infallible;
+    type Error = Error;
 
     async fn from_request(request: &'r Request<'_>) -> request::Outcome<User, Self::Error> {
-        use crate::rocket::outcome::IntoOutcome;
-        request
-            .cookies()
-            .get_private("user")
-            .and_then(|cookie| serde_json::from_str(cookie.value()).unwrap())
-            .or_forward(())
+        request.guard::<UserSession>().await.map(|session| session.user)
     }
 }
 
@@ -195,52 +718,682 @@ async fn user(user: User) -> Result<Json<User>> {
     Ok(Json(user))
 }
 
+#[get("/settings")]
+async fn settings(mut db: Connection<Db>, user: User) -> Result<Json<UserSettings>> {
+    let settings = sqlx::query!("SELECT dedup_titles, prefix_titles FROM users WHERE id = ?", user.id)
+        .fetch_one(&mut *db)
+        .await
+        .map(|r| UserSettings {
+            dedup_titles: r.dedup_titles,
+            prefix_titles: r.prefix_titles,
+        })?;
+
+    Ok(Json(settings))
+}
+
+#[post("/settings", data = "<settings>")]
+async fn update_settings(mut db: Connection<Db>, user: User, settings: Json<UserSettings>) -> Result<NoContent> {
+    sqlx::query!(
+        "UPDATE users SET dedup_titles = ?, prefix_titles = ? WHERE id = ?",
+        settings.dedup_titles,
+        settings.prefix_titles,
+        user.id
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(NoContent)
+}
+
+/// Changes the password of the user, which logs out all other devices
+#[post("/password", data = "<form>")]
+async fn change_password(
+    mut db: Connection<Db>,
+    session: UserSession,
+    form: Json<PasswordChangeForm>,
+    limiters: &State<AuthLimiters>,
+) -> Result<NoContent> {
+    let user_id = session.user.id.unwrap_or_default().to_string();
+    limiters.password_user.check(&user_id)?;
+    let r = sqlx::query!(
+        r#"SELECT password AS "password: Vec<u8>" FROM users WHERE id = ?"#,
+        session.user.id
+    )
+    .fetch_one(&mut *db)
+    .await?;
+    if verify_password(&form.current_password, &r.password).await? == PasswordMatch::Mismatch {
+        limiters.password_user.record_failure(&user_id);
+        return Err(Error::Custom("Incorrect password".to_owned()));
+    }
+    limiters.password_user.reset(&user_id);
+    validate_password(&form.new_password)?;
+
+    let hashed_password = hash_password(&form.new_password).await?;
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "UPDATE users SET password = ? WHERE id = ?",
+        hashed_password,
+        session.user.id
+    )
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = ? AND id != ?",
+        session.user.id,
+        session.id
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(NoContent)
+}
+
+/// Mails a password reset link to the user of `email` in the background, so
+/// that neither the response nor its timing tells whether the email is
+/// registered, and failures of the mail server aren't exposed either.
+#[post("/password/reset", data = "<form>")]
+async fn request_password_reset(
+    db: &State<Db>,
+    form: Json<PasswordResetRequest>,
+    client_ip: ClientIp,
+    limiters: &State<AuthLimiters>,
+    mail: &State<Mail>,
+    site: &State<SiteConfig>,
+) -> Result<NoContent> {
+    limiters.record_reset(&client_ip, &form.email)?;
+    let pool = db.0.clone();
+    let mailer = mail.mailer.clone();
+    let base_url = site.base_url.clone();
+    rocket::tokio::spawn(async move {
+        if let Err(e) = send_password_reset(&pool, &*mailer, &base_url, &form.email).await {
+            warn!("Failed to mail a password reset link: {}", e);
+        }
+    });
+    Ok(NoContent)
+}
+
+async fn send_password_reset(pool: &SqlitePool, mailer: &dyn Mailer, base_url: &str, email: &str) -> Result<()> {
+    let user = sqlx::query!("SELECT id, email FROM users WHERE email = ?", email)
+        .fetch_optional(pool)
+        .await?;
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+
+    sqlx::query!("DELETE FROM password_resets WHERE expires_at <= datetime('now')")
+        .execute(pool)
+        .await?;
+    let token = crate::util::gen_rand_token();
+    let token_hash = hash_reset_token(&token);
+    let lifetime = format!("+{PASSWORD_RESET_LIFETIME_MINUTES} minutes");
+    sqlx::query!(
+        "INSERT INTO password_resets (user_id, token_hash, expires_at) VALUES (?, ?, datetime('now', ?))",
+        user.id,
+        token_hash,
+        lifetime
+    )
+    .execute(pool)
+    .await?;
+
+    let link = format!("{}/?reset_token={token}", base_url.trim_end_matches('/'));
+    let body = format!(
+        "Someone, hopefully you, asked to reset the password of your MIOR account.\n\n\
+        Open the link below within {PASSWORD_RESET_LIFETIME_MINUTES} minutes to set a new password:\n\n\
+        {link}\n\n\
+        If it wasn't you, just ignore this mail and your password stays the same.\n"
+    );
+    mailer.send(&user.email, "Reset your MIOR password", body).await
+}
+
+/// Sets a new password with the token of a mailed reset link, which logs out
+/// all devices. The token can be used only once.
+#[post("/password/reset/confirm", data = "<form>")]
+async fn reset_password(mut db: Connection<Db>, form: Json<PasswordResetForm>) -> Result<NoContent> {
+    validate_password(&form.new_password)?;
+    let token_hash = hash_reset_token(&form.token);
+    let hashed_password = hash_password(&form.new_password).await?;
+    let mut tx = db.begin().await?;
+    let user_id = sqlx::query!(
+        "DELETE FROM password_resets WHERE token_hash = ? AND expires_at > datetime('now') RETURNING user_id",
+        token_hash
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    .map(|r| r.user_id)
+    .ok_or_else(|| Error::Custom("Invalid or expired reset link".to_owned()))?;
+
+    sqlx::query!("UPDATE users SET password = ? WHERE id = ?", hashed_password, user_id)
+        .execute(&mut tx)
+        .await?;
+    // Other links mailed before are revoked as well
+    sqlx::query!("DELETE FROM password_resets WHERE user_id = ?", user_id)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query!("DELETE FROM sessions WHERE user_id = ?", user_id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(NoContent)
+}
+
+/// Reset tokens are stored hashed, so that they can't be used if the
+/// database leaks
+fn hash_reset_token(token: &str) -> String {
+    use sha2::Digest;
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(token.as_bytes());
+    hasher.finalize().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Replaces the token of the merged feed with a new one. The old token keeps
+/// working for `grace_hours` (none by default), so that readers can be
+/// updated in the meantime.
+#[post("/token?<grace_hours>")]
+async fn rotate_token(mut db: Connection<Db>, user: User, grace_hours: Option<u32>) -> Result<Json<User>> {
+    let grace = token_grace(grace_hours)?;
+    let token = crate::util::gen_rand_token();
+    sqlx::query!(
+        "UPDATE users SET token = ?1, previous_token = CASE WHEN ?2 IS NULL THEN NULL ELSE token END, \
+        previous_token_expires_at = datetime('now', ?2) \
+        WHERE id = ?3",
+        token,
+        grace,
+        user.id
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(Json(User {
+        token: Some(token),
+        ..user
+    }))
+}
+
+/// Converts the grace period of a rotated token into a modifier of
+/// `datetime()`, or `None` if the old token stops working right away
+fn token_grace(grace_hours: Option<u32>) -> Result<Option<String>> {
+    let grace_hours = grace_hours.unwrap_or_default();
+    if grace_hours > MAX_TOKEN_GRACE_HOURS {
+        return Err(Error::Custom(format!(
+            "Grace period is longer than {MAX_TOKEN_GRACE_HOURS} hours"
+        )));
+    }
+    // `datetime('now', NULL)` is NULL as well
+    Ok((grace_hours > 0).then(|| format!("+{grace_hours} hours")))
+}
+
+/// Stops the previous token of the merged feed from working before its grace
+/// period ends
+#[delete("/token/previous")]
+async fn revoke_previous_token(mut db: Connection<Db>, user: User) -> Result<NoContent> {
+    sqlx::query!(
+        "UPDATE users SET previous_token = NULL, previous_token_expires_at = NULL WHERE id = ?",
+        user.id
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(NoContent)
+}
+
 #[get("/user", rank = 2)]
 async fn user_no_auth() -> Result<Json<User>> {
     Err(Error::Unauthorized)
 }
 
 #[post("/logout")]
-async fn logout(cookie: &CookieJar<'_>) -> Result<()> {
-    cookie.remove_private(Cookie::named("user"));
+async fn logout(mut db: Connection<Db>, cookie: &CookieJar<'_>) -> Result<()> {
+    if let Some(session_cookie) = cookie.get_private(SESSION_COOKIE) {
+        let token = session_cookie.value();
+        sqlx::query!("DELETE FROM sessions WHERE token = ?", token)
+            .execute(&mut *db)
+            .await?;
+    }
+    cookie.remove_private(Cookie::named(SESSION_COOKIE));
     Ok(())
 }
 
-#[get("/rss?<token>")]
-async fn rss(mut db: Connection<Db>, token: &str, ua: UserAgent<'_>) -> Result<(ContentType, Vec<u8>)> {
+/// Lists the unexpired login sessions of the user, most recently used first
+#[get("/")]
+async fn list_sessions(mut db: Connection<Db>, session: UserSession) -> Result<Json<Vec<Session>>> {
+    let sessions = sqlx::query!(
+        "SELECT id, user_agent, created_at AS \"created_at: DateTime<Utc>\", \
+        last_seen_at AS \"last_seen_at: DateTime<Utc>\", expires_at AS \"expires_at: DateTime<Utc>\" \
+        FROM sessions \
+        WHERE user_id = ? AND expires_at > datetime('now') \
+        ORDER BY last_seen_at DESC",
+        session.user.id
+    )
+    .fetch(&mut *db)
+    .map_ok(|r| Session {
+        id: r.id,
+        user_agent: r.user_agent,
+        created_at: r.created_at,
+        last_seen_at: r.last_seen_at,
+        expires_at: r.expires_at,
+        current: r.id == session.id,
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(Json(sessions))
+}
+
+/// Logs out one device of the user, possibly the current one
+#[delete("/<id>")]
+async fn revoke_session(
+    mut db: Connection<Db>,
+    session: UserSession,
+    id: i64,
+    cookie: &CookieJar<'_>,
+) -> Result<NoContent> {
+    let result = sqlx::query!("DELETE FROM sessions WHERE id = ? AND user_id = ?", id, session.user.id)
+        .execute(&mut *db)
+        .await?;
+    if result.rows_affected() != 1 {
+        return Err(Error::SessionNotFound(id));
+    }
+
+    if id == session.id {
+        cookie.remove_private(Cookie::named(SESSION_COOKIE));
+    }
+    Ok(NoContent)
+}
+
+/// Logs out everywhere, including the current device
+#[delete("/")]
+async fn revoke_all_sessions(mut db: Connection<Db>, user: User, cookie: &CookieJar<'_>) -> Result<NoContent> {
+    sqlx::query!("DELETE FROM sessions WHERE user_id = ?", user.id)
+        .execute(&mut *db)
+        .await?;
+
+    cookie.remove_private(Cookie::named(SESSION_COOKIE));
+    Ok(NoContent)
+}
+
+#[get("/rss?<token>&<format>")]
+async fn rss(
+    mut db: Connection<Db>,
+    token: &str,
+    format: Option<OutputFormat>,
+    ua: UserAgent<'_>,
+    conditions: Conditions<'_>,
+    request_url: RequestUrl,
+    site: &State<SiteConfig>,
+) -> Result<MergedFeedResponse> {
     if let Some(s) = ua.0 {
         if s.contains("mior") {
             return Err(Error::Custom("Request from mior is forbidden".to_owned()));
         }
     }
 
-    let feeds: Vec<SourceFeed> = sqlx::query!(
-        "SELECT feeds.id AS id, name, url, keywords \
-        FROM feeds, users \
-        WHERE feeds.user_id = users.id AND users.token = ?",
-        token
+    let target = MergedFeedTarget::resolve(&mut db, token).await?;
+
+    // Without a collection, all the feeds of the user are merged
+    let items = sqlx::query!(
+        "SELECT items.id AS id, items.data AS data, items.updated_at AS updated_at, \
+        feeds.name AS feed_name, feeds.url AS feed_url \
+        FROM items, feeds \
+        WHERE items.feed_id = feeds.id AND feeds.user_id = ?1 \
+        AND (?2 IS NULL OR feeds.id IN (SELECT feed_id FROM collection_feeds WHERE collection_id = ?2)) \
+        ORDER BY items.pub_date DESC \
+        LIMIT ?3",
+        target.user_id,
+        target.collection_id,
+        MERGED_ITEMS_LIMIT
     )
     .fetch(&mut *db)
-    .map_ok(|r| SourceFeed {
-        id: Some(r.id),
-        name: r.name,
-        url: r.url,
-        keywords: r.keywords,
+    .map_ok(|r| StoredItem {
+        id: r.id,
+        data: r.data,
+        updated_at: Utc.from_utc_datetime(&r.updated_at),
+        source: EntrySource {
+            name: r.feed_name,
+            url: r.feed_url,
+        },
     })
     .try_collect::<Vec<_>>()
     .await?;
 
-    // Put back the DB connection instantly since fetching feeds may take long
-    drop(db);
+    let failed_feeds = sqlx::query!(
+        "SELECT id FROM feeds \
+        WHERE user_id = ?1 AND fetch_error IS NOT NULL \
+        AND (?2 IS NULL OR id IN (SELECT feed_id FROM collection_feeds WHERE collection_id = ?2))",
+        target.user_id,
+        target.collection_id
+    )
+    .fetch(&mut *db)
+    .map_ok(|r| r.id)
+    .try_collect::<Vec<_>>()
+    .await?;
 
-    merge_feeds_data(&feeds, FEED_ITEMS_LIMIT)
-        .await
-        .map(|r| (ContentType::XML, r))
+    let format = format.unwrap_or(OutputFormat::Rss);
+    let content_type = match format {
+        OutputFormat::Rss => ContentType::XML,
+        OutputFormat::Atom => ContentType::new("application", "atom+xml"),
+        OutputFormat::Json => ContentType::new("application", "feed+json"),
+    };
+    let etag = merged_feed_etag(&items, &failed_feeds, &target, format);
+    let last_modified = merged_feed_last_modified(&items, &failed_feeds, &target);
+    if conditions.is_not_modified(&etag, last_modified) {
+        return Ok(MergedFeedResponse {
+            content_type,
+            data: None,
+            failed_feeds,
+            etag,
+            last_modified,
+        });
+    }
+
+    let mut merger = FeedMerger::new()
+        .with_id(target.feed_id(&site.base_url))
+        .with_ttl(MERGED_FEED_MAX_AGE / 60)
+        .with_dedup_titles(target.settings.dedup_titles)
+        .with_prefix_titles(target.settings.prefix_titles);
+    if let Some(title) = target.title {
+        merger = merger.with_title(title);
+    }
+    if let Some(description) = target.description {
+        merger = merger.with_description(description);
+    }
+    if let Some(url) = request_url.0 {
+        merger = merger.with_self_link(url);
+    }
+    for item in &items {
+        match serde_json::from_str::<FeedEntry>(&item.data) {
+            Ok(entry) => merger.append(&item.source, vec![entry]),
+            Err(e) => warn_!("Skipped malformed item {}: {}", item.id, e),
+        }
+    }
+
+    Ok(MergedFeedResponse {
+        content_type,
+        data: Some(merger.build(format)),
+        failed_feeds,
+        etag,
+        last_modified,
+    })
+}
+
+/// Merged feed which a token of `/rss` refers to, either a collection or all
+/// the feeds of a user
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct MergedFeedTarget {
+    user_id: i64,
+
+    collection_id: Option<i64>,
+
+    /// Title of the channel, if other than the default one
+    title: Option<String>,
+
+    description: Option<String>,
+
+    settings: UserSettings,
+
+    /// Time of the latest change of the settings, the collection or the feeds
+    /// other than new items, which is covered by the ETag otherwise
+    #[serde(skip)]
+    changed_at: DateTime<Utc>,
+}
+
+impl MergedFeedTarget {
+    /// Tag URI of the merged feed, which unlike its URL is kept when the token
+    /// is rotated, e.g. `tag:example.com,2022:collection/1`
+    fn feed_id(&self, base_url: &str) -> String {
+        let host = Url::parse(base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+            .unwrap_or_else(|| "localhost".to_owned());
+        match self.collection_id {
+            Some(id) => format!("tag:{host},2022:collection/{id}"),
+            None => format!("tag:{host},2022:user/{}", self.user_id),
+        }
+    }
+
+    async fn resolve(db: &mut Connection<Db>, token: &str) -> Result<Self> {
+        let collection = sqlx::query!(
+            "SELECT collections.id AS id, collections.name AS name, collections.description AS description, \
+            collections.updated_at AS updated_at, users.id AS user_id, users.dedup_titles AS dedup_titles, \
+            users.prefix_titles AS prefix_titles, users.updated_at AS user_updated_at \
+            FROM collections, users \
+            WHERE collections.user_id = users.id AND (collections.token = ?1 \
+            OR (collections.previous_token = ?1 AND collections.previous_token_expires_at > datetime('now')))",
+            token
+        )
+        .fetch_optional(&mut **db)
+        .await?;
+        if let Some(r) = collection {
+            return Ok(MergedFeedTarget {
+                user_id: r.user_id,
+                collection_id: Some(r.id),
+                title: Some(r.name),
+                description: Some(r.description),
+                settings: UserSettings {
+                    dedup_titles: r.dedup_titles,
+                    prefix_titles: r.prefix_titles,
+                },
+                changed_at: Utc.from_utc_datetime(&r.updated_at.max(r.user_updated_at)),
+            });
+        }
+
+        sqlx::query!(
+            "SELECT id, dedup_titles, prefix_titles, updated_at FROM users \
+            WHERE token = ?1 OR (previous_token = ?1 AND previous_token_expires_at > datetime('now'))",
+            token
+        )
+        .fetch_optional(&mut **db)
+        .await?
+        .map(|r| MergedFeedTarget {
+            user_id: r.id,
+            collection_id: None,
+            title: None,
+            description: None,
+            settings: UserSettings {
+                dedup_titles: r.dedup_titles,
+                prefix_titles: r.prefix_titles,
+            },
+            changed_at: Utc.from_utc_datetime(&r.updated_at),
+        })
+        .ok_or(Error::Unauthorized)
+    }
+}
+
+/// Item of the merged feed loaded from the database
+struct StoredItem {
+    id: i64,
+
+    /// Serialized `FeedEntry`
+    data: String,
+
+    updated_at: DateTime<Utc>,
+
+    source: EntrySource,
+}
+
+/// Computes the ETag of a merged feed from the identities and modification
+/// times of its items, so that it stays the same until the poller stores a
+/// change
+fn merged_feed_etag(
+    items: &[StoredItem],
+    failed_feeds: &[i64],
+    target: &MergedFeedTarget,
+    format: OutputFormat,
+) -> String {
+    use sha2::Digest;
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(format!("{format:?}"));
+    hasher.update(serde_json::to_vec(target).unwrap());
+    for item in items {
+        hasher.update(item.id.to_le_bytes());
+        hasher.update(item.updated_at.timestamp().to_le_bytes());
+    }
+    hasher.update(b"failed");
+    for id in failed_feeds {
+        hasher.update(id.to_le_bytes());
+    }
+    let hash: String = hasher.finalize()[..16].iter().map(|b| format!("{b:02x}")).collect();
+    format!("\"{hash}\"")
+}
+
+/// Returns the time of the latest change of a merged feed, unless some of its
+/// feeds failed. Failures come and go without any date moving, so that only
+/// the ETag can tell clients a new list of failed feeds.
+fn merged_feed_last_modified(
+    items: &[StoredItem],
+    failed_feeds: &[i64],
+    target: &MergedFeedTarget,
+) -> Option<DateTime<Utc>> {
+    if !failed_feeds.is_empty() {
+        return None;
+    }
+    items
+        .iter()
+        .map(|item| item.updated_at)
+        .chain(std::iter::once(target.changed_at))
+        .max()
+}
+
+/// Name of the response header listing IDs of the feeds left out from the
+/// merged feed
+const FAILED_FEEDS_HEADER: &str = "X-Mior-Failed-Feeds";
+
+struct MergedFeedResponse {
+    content_type: ContentType,
+
+    /// Body of the merged feed, or `None` to respond with `304 Not Modified`
+    data: Option<Vec<u8>>,
+
+    /// IDs of feeds which failed to be fetched or parsed in the last poll
+    failed_feeds: Vec<i64>,
+
+    etag: String,
+
+    /// Time of the latest change of the items or the feeds they come from,
+    /// unless some feeds failed
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl<'r> Responder<'r, 'static> for MergedFeedResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = match self.data {
+            Some(data) => (self.content_type, data).respond_to(request)?,
+            None => Response::build().status(Status::NotModified).finalize(),
+        };
+        response.set_raw_header("ETag", self.etag);
+        if let Some(last_modified) = self.last_modified {
+            response.set_raw_header("Last-Modified", http_date(last_modified));
+        }
+        response.set_raw_header("Cache-Control", format!("max-age={MERGED_FEED_MAX_AGE}"));
+        if !self.failed_feeds.is_empty() {
+            let ids: Vec<_> = self.failed_feeds.iter().map(|id| id.to_string()).collect();
+            response.set_raw_header(FAILED_FEEDS_HEADER, ids.join(","));
+        }
+        Ok(response)
+    }
+}
+
+/// Formats `time` as an HTTP-date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
 }
 
 use rocket::outcome::Outcome::Success;
 use rocket::request::Outcome;
 
+/// Conditional request headers of a client revalidating its cached response
+struct Conditions<'r> {
+    if_none_match: Option<&'r str>,
+    if_modified_since: Option<&'r str>,
+}
+
+impl Conditions<'_> {
+    /// Whether the cached response of the client is still fresh. As specified
+    /// by RFC 7232, `If-Modified-Since` is ignored when `If-None-Match` is
+    /// present.
+    fn is_not_modified(&self, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+        if let Some(if_none_match) = self.if_none_match {
+            return if_none_match
+                .split(',')
+                .map(|t| t.trim().trim_start_matches("W/"))
+                .any(|t| t == "*" || t == etag);
+        }
+        match (self.if_modified_since, last_modified) {
+            (Some(since), Some(last_modified)) => DateTime::parse_from_rfc2822(since)
+                .map(|since| last_modified.timestamp() <= since.timestamp())
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<'r> FromRequest<'r> for Conditions<'r> {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Success(Conditions {
+            if_none_match: request.headers().get_one("If-None-Match"),
+            if_modified_since: request.headers().get_one("If-Modified-Since"),
+        })
+    }
+}
+
+/// Absolute URL of the request under the configured public URL of the site,
+/// which unlike `Host` can't be spoofed by clients
+struct RequestUrl(Option<String>);
+
+#[async_trait::async_trait]
+impl<'r> FromRequest<'r> for RequestUrl {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let url = request
+            .rocket()
+            .state::<SiteConfig>()
+            .map(|site| format!("{}{}", site.base_url.trim_end_matches('/'), request.uri()));
+        Success(RequestUrl(url))
+    }
+}
+
+/// Client IP keying rate limits, which is the peer address unless
+/// `proxy_ip_header` of [`SiteConfig`] names a header set by a trusted reverse
+/// proxy, since clients can send any header themselves. IPv6 addresses are
+/// cut to their /64 prefix, which usually belongs to one client as a whole.
+struct ClientIp(Option<String>);
+
+#[async_trait::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let header = request
+            .rocket()
+            .state::<SiteConfig>()
+            .and_then(|site| site.proxy_ip_header.as_deref());
+        let ip = match header {
+            // The proxy appends the address it sees to lists like `X-Forwarded-For`
+            Some(name) => request
+                .headers()
+                .get(name)
+                .last()
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse().ok()),
+            None => request.remote().map(|addr| addr.ip()),
+        };
+        Success(ClientIp(ip.map(|ip| match ip {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => {
+                let s = ip.segments();
+                format!("{}/64", Ipv6Addr::new(s[0], s[1], s[2], s[3], 0, 0, 0, 0))
+            }
+        })))
+    }
+}
+
 struct UserAgent<'r>(Option<&'r str>);
 
 #[async_trait::async_trait]
@@ -255,9 +1408,599 @@ impl<'r> FromRequest<'r> for UserAgent<'r> {
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("Routes", |rocket| async {
         rocket
+            .manage(AuthLimiters::default())
             .mount("/api/feeds", routes![list, create, update, delete])
             .mount("/api/", routes![register, login, user, user_no_auth, logout])
-            .mount("/api/", routes![fetch])
+            .mount("/api/", routes![settings, update_settings])
+            .mount("/api/", routes![rotate_token, revoke_previous_token])
+            .mount(
+                "/api/",
+                routes![change_password, request_password_reset, reset_password],
+            )
+            .mount(
+                "/api/collections",
+                routes![
+                    list_collections,
+                    create_collection,
+                    update_collection,
+                    delete_collection,
+                    rotate_collection_token,
+                    revoke_previous_collection_token
+                ],
+            )
+            .mount(
+                "/api/sessions",
+                routes![list_sessions, revoke_session, revoke_all_sessions],
+            )
+            .mount("/api/", routes![fetch, fetch_url])
             .mount("/", routes![rss])
     })
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::Duration;
+    use rocket::local::asynchronous::{Client, LocalRequest};
+
+    use super::*;
+    use crate::util::gen_rand_token;
+
+    /// Mailer keeping the mails instead of sending them
+    #[derive(Default)]
+    pub(crate) struct RecordingMailer {
+        /// Recipients and bodies of the mails sent so far
+        pub(crate) sent: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Mailer for RecordingMailer {
+        async fn send(&self, to: &str, _subject: &str, body: String) -> Result<()> {
+            self.sent.lock().unwrap().push((to.to_owned(), body));
+            Ok(())
+        }
+    }
+
+    /// Client of the routes on a fresh database, logged in as a new user
+    pub(crate) struct TestClient {
+        pub(crate) client: Client,
+        pub(crate) db: SqlitePool,
+        pub(crate) user_id: i64,
+        /// Token of the merged feed of the user
+        pub(crate) token: String,
+        pub(crate) session_token: String,
+        pub(crate) mailer: Arc<RecordingMailer>,
+    }
+
+    impl TestClient {
+        pub(crate) async fn new() -> Self {
+            let db_path = std::env::temp_dir().join(format!("mior-test-{}.sqlite", gen_rand_token()));
+            let figment = rocket::Config::figment()
+                .merge(("databases.sqlx.url", format!("sqlite://{}?mode=rwc", db_path.display())));
+            let mailer = Arc::new(RecordingMailer::default());
+            let rocket = rocket::custom(figment)
+                .attach(Db::init())
+                .attach(AdHoc::config::<SiteConfig>())
+                .attach(AdHoc::try_on_ignite("Run Migrations", crate::run_migrations))
+                .manage(Mail { mailer: mailer.clone() })
+                .attach(stage());
+            let client = Client::tracked(rocket).await.unwrap();
+            let db = Db::fetch(client.rocket()).unwrap().0.clone();
+
+            let token = gen_rand_token();
+            let user_id = sqlx::query("INSERT INTO users (email, nickname, password, token) VALUES (?, 'test', '', ?)")
+                .bind(format!("{token}@example.com"))
+                .bind(&token)
+                .execute(&db)
+                .await
+                .unwrap()
+                .last_insert_rowid();
+            let session_token = gen_rand_token();
+            sqlx::query("INSERT INTO sessions (user_id, token, expires_at) VALUES (?, ?, datetime('now', '+1 day'))")
+                .bind(user_id)
+                .bind(&session_token)
+                .execute(&db)
+                .await
+                .unwrap();
+
+            TestClient {
+                client,
+                db,
+                user_id,
+                token,
+                session_token,
+                mailer,
+            }
+        }
+
+        /// Waits for the `n`th mail sent in the background, and returns its
+        /// recipient and body
+        async fn wait_for_mail(&self, n: usize) -> (String, String) {
+            for _ in 0..100 {
+                if let Some(mail) = self.mailer.sent.lock().unwrap().get(n - 1) {
+                    return mail.clone();
+                }
+                rocket::tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            panic!("Mail {n} is not sent");
+        }
+
+        /// Status of getting the user with the session of `session_token`
+        async fn user_status(&self, session_token: &str) -> Status {
+            self.client
+                .get("/api/user")
+                .private_cookie(Cookie::new(SESSION_COOKIE, session_token.to_owned()))
+                .dispatch()
+                .await
+                .status()
+        }
+
+        /// Status of getting the merged feed of `token`
+        async fn rss_status(&self, token: &str) -> Status {
+            let uri = format!("/rss?token={token}");
+            self.client.get(uri).dispatch().await.status()
+        }
+
+        /// Sets the password of the user
+        async fn set_password(&self, password: &str) {
+            sqlx::query("UPDATE users SET password = ? WHERE id = ?")
+                .bind(hash_password(password).await.unwrap())
+                .bind(self.user_id)
+                .execute(&self.db)
+                .await
+                .unwrap();
+        }
+
+        async fn password_matches(&self, password: &str) -> bool {
+            let hashed = sqlx::query_scalar::<_, Vec<u8>>("SELECT password FROM users WHERE id = ?")
+                .bind(self.user_id)
+                .fetch_one(&self.db)
+                .await
+                .unwrap();
+            verify_password(password, &hashed).await.unwrap() == PasswordMatch::Match
+        }
+
+        /// Posts `body` as JSON on behalf of the user
+        pub(crate) fn post<'c>(&'c self, uri: &'c str, body: &str) -> LocalRequest<'c> {
+            self.client
+                .post(uri)
+                .header(ContentType::JSON)
+                .private_cookie(Cookie::new(SESSION_COOKIE, self.session_token.clone()))
+                .body(body)
+        }
+
+        /// Gets `uri` on behalf of the user
+        pub(crate) fn get<'c>(&'c self, uri: &'c str) -> LocalRequest<'c> {
+            self.client
+                .get(uri)
+                .private_cookie(Cookie::new(SESSION_COOKIE, self.session_token.clone()))
+        }
+
+        /// Deletes `uri` on behalf of the user
+        pub(crate) fn delete<'c>(&'c self, uri: &'c str) -> LocalRequest<'c> {
+            self.client
+                .delete(uri)
+                .private_cookie(Cookie::new(SESSION_COOKIE, self.session_token.clone()))
+        }
+    }
+
+    /// Legacy keywords which don't parse as a keyword query stay matched as
+    /// plain words on updates of the other settings
+    #[rocket::async_test]
+    async fn test_update_legacy_feed() {
+        let client = TestClient::new().await;
+        let id = sqlx::query(
+            "INSERT INTO feeds (name, url, keywords, user_id, legacy_keywords) \
+            VALUES ('Anime', 'https://example.com/rss', ?, ?, 1)",
+        )
+        .bind("\"1080p")
+        .bind(client.user_id)
+        .execute(&client.db)
+        .await
+        .unwrap()
+        .last_insert_rowid();
+        let stored = || {
+            sqlx::query_as::<_, (String, bool)>("SELECT name, legacy_keywords FROM feeds WHERE id = ?")
+                .bind(id)
+                .fetch_one(&client.db)
+        };
+        let uri = format!("/api/feeds/{id}");
+
+        let feed = r#"{"name": "Renamed", "url": "https://example.com/rss", "keywords": "\"1080p", "timezone": "Asia/Shanghai"}"#;
+        assert_eq!(client.post(&uri, feed).dispatch().await.status(), Status::NoContent);
+        assert_eq!(stored().await.unwrap(), ("Renamed".to_owned(), true));
+
+        // Edited keywords are parsed as a keyword query
+        let feed = r#"{"name": "Renamed", "url": "https://example.com/rss", "keywords": "(a"}"#;
+        assert_eq!(client.post(&uri, feed).dispatch().await.status(), Status::BadRequest);
+        let feed = r#"{"name": "Renamed", "url": "https://example.com/rss", "keywords": "\"1080p\" a"}"#;
+        assert_eq!(client.post(&uri, feed).dispatch().await.status(), Status::NoContent);
+        assert_eq!(stored().await.unwrap(), ("Renamed".to_owned(), false));
+    }
+
+    /// Renames keep the stored items, while other edits drop them until the
+    /// feed is polled anew
+    #[rocket::async_test]
+    async fn test_update_feed_items() {
+        let client = TestClient::new().await;
+        let id = sqlx::query(
+            "INSERT INTO feeds (name, url, keywords, user_id, fetched_at, etag) \
+            VALUES ('Anime', 'https://example.com/rss', '', ?, CURRENT_TIMESTAMP, '\"v1\"')",
+        )
+        .bind(client.user_id)
+        .execute(&client.db)
+        .await
+        .unwrap()
+        .last_insert_rowid();
+        sqlx::query("INSERT INTO items (feed_id, guid, pub_date, data) VALUES (?, 'a', CURRENT_TIMESTAMP, '{}')")
+            .bind(id)
+            .execute(&client.db)
+            .await
+            .unwrap();
+        let stored = || {
+            sqlx::query_as::<_, (i64, Option<String>)>(
+                "SELECT (SELECT count(*) FROM items WHERE feed_id = feeds.id), etag FROM feeds WHERE id = ?",
+            )
+            .bind(id)
+            .fetch_one(&client.db)
+        };
+        let uri = format!("/api/feeds/{id}");
+
+        let feed = r#"{"name": "Renamed", "url": "https://example.com/rss", "keywords": ""}"#;
+        assert_eq!(client.post(&uri, feed).dispatch().await.status(), Status::NoContent);
+        assert_eq!(stored().await.unwrap(), (1, Some("\"v1\"".to_owned())));
+
+        let feed = r#"{"name": "Renamed", "url": "https://example.com/rss", "keywords": "1080p"}"#;
+        assert_eq!(client.post(&uri, feed).dispatch().await.status(), Status::NoContent);
+        assert_eq!(stored().await.unwrap(), (0, None));
+    }
+
+    #[rocket::async_test]
+    async fn test_collection_with_duplicate_feeds() {
+        let client = TestClient::new().await;
+        let feed_id = sqlx::query(
+            "INSERT INTO feeds (name, url, keywords, user_id) VALUES ('Anime', 'https://example.com/rss', '', ?)",
+        )
+        .bind(client.user_id)
+        .execute(&client.db)
+        .await
+        .unwrap()
+        .last_insert_rowid();
+        let collection_feeds = || {
+            sqlx::query_scalar::<_, i64>(
+                "SELECT feed_id FROM collection_feeds, collections \
+                WHERE collection_feeds.collection_id = collections.id AND collections.user_id = ?",
+            )
+            .bind(client.user_id)
+            .fetch_all(&client.db)
+        };
+
+        let collection = format!(r#"{{"name": "Anime", "feeds": [{feed_id}, {feed_id}]}}"#);
+        let resp = client.post("/api/collections", &collection).dispatch().await;
+        assert_eq!(resp.status(), Status::Created);
+        assert_eq!(collection_feeds().await.unwrap(), [feed_id]);
+
+        let id = sqlx::query_scalar::<_, i64>("SELECT id FROM collections WHERE user_id = ?")
+            .bind(client.user_id)
+            .fetch_one(&client.db)
+            .await
+            .unwrap();
+        let uri = format!("/api/collections/{id}");
+        let resp = client.post(&uri, &collection).dispatch().await;
+        assert_eq!(resp.status(), Status::NoContent);
+        assert_eq!(collection_feeds().await.unwrap(), [feed_id]);
+    }
+
+    /// Mailed reset links set a new password once, within their lifetime, and
+    /// log out all devices
+    #[rocket::async_test]
+    async fn test_reset_password() {
+        let client = TestClient::new().await;
+        client.set_password("old password").await;
+        let email = format!("{}@example.com", client.token);
+        let request = format!(r#"{{"email": "{email}"}}"#);
+        let reset_token = |body: &str| {
+            let link = body.split_whitespace().find(|s| s.contains("reset_token=")).unwrap();
+            link.rsplit('=').next().unwrap().to_owned()
+        };
+        let confirm = |token: &str| {
+            let form = format!(r#"{{"token": "{token}", "new_password": "new password"}}"#);
+            let client = &client;
+            async move {
+                client
+                    .post("/api/password/reset/confirm", &form)
+                    .dispatch()
+                    .await
+                    .status()
+            }
+        };
+
+        let resp = client.post("/api/password/reset", &request).dispatch().await;
+        assert_eq!(resp.status(), Status::NoContent);
+        let (to, body) = client.wait_for_mail(1).await;
+        assert_eq!(to, email);
+        sqlx::query("UPDATE password_resets SET expires_at = datetime('now', '-1 minute')")
+            .execute(&client.db)
+            .await
+            .unwrap();
+        assert_eq!(confirm(&reset_token(&body)).await, Status::BadRequest);
+        assert!(client.password_matches("old password").await);
+
+        let resp = client.post("/api/password/reset", &request).dispatch().await;
+        assert_eq!(resp.status(), Status::NoContent);
+        let (_, body) = client.wait_for_mail(2).await;
+        let token = reset_token(&body);
+        assert_eq!(confirm(&token).await, Status::NoContent);
+        assert!(client.password_matches("new password").await);
+        assert_eq!(client.get("/api/user").dispatch().await.status(), Status::Unauthorized);
+
+        // The link can't be used again
+        client.set_password("newer password").await;
+        assert_eq!(confirm(&token).await, Status::BadRequest);
+        assert!(client.password_matches("newer password").await);
+    }
+
+    /// Changing the password needs the current one, and logs out the other
+    /// devices
+    #[rocket::async_test]
+    async fn test_change_password() {
+        let client = TestClient::new().await;
+        client.set_password("old password").await;
+        sqlx::query("INSERT INTO sessions (user_id, token, expires_at) VALUES (?, ?, datetime('now', '+1 day'))")
+            .bind(client.user_id)
+            .bind(gen_rand_token())
+            .execute(&client.db)
+            .await
+            .unwrap();
+        let sessions = || {
+            sqlx::query_scalar::<_, i64>("SELECT count(*) FROM sessions WHERE user_id = ?")
+                .bind(client.user_id)
+                .fetch_one(&client.db)
+        };
+
+        let form = r#"{"current_password": "wrong password", "new_password": "new password"}"#;
+        let resp = client.post("/api/password", form).dispatch().await;
+        assert_eq!(resp.status(), Status::BadRequest);
+        assert!(client.password_matches("old password").await);
+        assert_eq!(sessions().await.unwrap(), 2);
+
+        let form = r#"{"current_password": "old password", "new_password": "new password"}"#;
+        let resp = client.post("/api/password", form).dispatch().await;
+        assert_eq!(resp.status(), Status::NoContent);
+        assert!(client.password_matches("new password").await);
+        assert_eq!(sessions().await.unwrap(), 1);
+        assert_eq!(client.get("/api/user").dispatch().await.status(), Status::Ok);
+    }
+
+    /// Devices can be logged out one by one or all at once, after which their
+    /// cookies are rejected
+    #[rocket::async_test]
+    async fn test_revoke_sessions() {
+        let client = TestClient::new().await;
+        client.set_password("password").await;
+        let login = format!(r#"{{"email": "{}@example.com", "password": "password"}}"#, client.token);
+        for _ in 0..2 {
+            let resp = client
+                .client
+                .post("/api/login")
+                .header(ContentType::JSON)
+                .body(&login)
+                .dispatch()
+                .await;
+            assert_eq!(resp.status(), Status::Ok);
+        }
+        let sessions = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, token FROM sessions WHERE user_id = ? AND token != ? ORDER BY id",
+        )
+        .bind(client.user_id)
+        .bind(&client.session_token)
+        .fetch_all(&client.db)
+        .await
+        .unwrap();
+        let [(revoked_id, revoked), (_, other)] = <[_; 2]>::try_from(sessions).unwrap();
+        assert_eq!(client.user_status(&revoked).await, Status::Ok);
+
+        let uri = format!("/api/sessions/{revoked_id}");
+        assert_eq!(client.delete(&uri).dispatch().await.status(), Status::NoContent);
+        assert_eq!(client.user_status(&revoked).await, Status::Unauthorized);
+        assert_eq!(client.user_status(&other).await, Status::Ok);
+        assert_eq!(client.user_status(&client.session_token).await, Status::Ok);
+
+        // Logs out everywhere
+        let resp = client.delete("/api/sessions").dispatch().await;
+        assert_eq!(resp.status(), Status::NoContent);
+        assert_eq!(client.user_status(&other).await, Status::Unauthorized);
+        assert_eq!(client.user_status(&client.session_token).await, Status::Unauthorized);
+    }
+
+    /// Sessions end when they expire or their user is deleted
+    #[rocket::async_test]
+    async fn test_ended_sessions() {
+        let client = TestClient::new().await;
+        assert_eq!(client.user_status(&client.session_token).await, Status::Ok);
+        sqlx::query("UPDATE sessions SET expires_at = datetime('now', '-1 minute') WHERE token = ?")
+            .bind(&client.session_token)
+            .execute(&client.db)
+            .await
+            .unwrap();
+        assert_eq!(client.user_status(&client.session_token).await, Status::Unauthorized);
+
+        let client = TestClient::new().await;
+        sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(client.user_id)
+            .execute(&client.db)
+            .await
+            .unwrap();
+        assert_eq!(client.user_status(&client.session_token).await, Status::Unauthorized);
+    }
+
+    /// Rotated tokens of the merged feed stop working right away, or when
+    /// their grace period ends
+    #[rocket::async_test]
+    async fn test_rotate_token() {
+        let client = TestClient::new().await;
+        let current_token = || {
+            sqlx::query_scalar::<_, String>("SELECT token FROM users WHERE id = ?")
+                .bind(client.user_id)
+                .fetch_one(&client.db)
+        };
+
+        let resp = client.post("/api/token", "").dispatch().await;
+        assert_eq!(resp.status(), Status::Ok);
+        let token = current_token().await.unwrap();
+        assert_eq!(client.rss_status(&client.token).await, Status::Unauthorized);
+        assert_eq!(client.rss_status(&token).await, Status::Ok);
+
+        let resp = client.post("/api/token?grace_hours=1", "").dispatch().await;
+        assert_eq!(resp.status(), Status::Ok);
+        let new_token = current_token().await.unwrap();
+        assert_eq!(client.rss_status(&token).await, Status::Ok);
+        assert_eq!(client.rss_status(&new_token).await, Status::Ok);
+
+        sqlx::query("UPDATE users SET previous_token_expires_at = datetime('now', '-1 minute') WHERE id = ?")
+            .bind(client.user_id)
+            .execute(&client.db)
+            .await
+            .unwrap();
+        assert_eq!(client.rss_status(&token).await, Status::Unauthorized);
+        assert_eq!(client.rss_status(&new_token).await, Status::Ok);
+    }
+
+    /// Tokens of collections are rotated like the one of the user
+    #[rocket::async_test]
+    async fn test_rotate_collection_token() {
+        let client = TestClient::new().await;
+        let old_token = gen_rand_token();
+        let id = sqlx::query("INSERT INTO collections (user_id, name, token) VALUES (?, 'Anime', ?)")
+            .bind(client.user_id)
+            .bind(&old_token)
+            .execute(&client.db)
+            .await
+            .unwrap()
+            .last_insert_rowid();
+        let current_token = || {
+            sqlx::query_scalar::<_, String>("SELECT token FROM collections WHERE id = ?")
+                .bind(id)
+                .fetch_one(&client.db)
+        };
+        assert_eq!(client.rss_status(&old_token).await, Status::Ok);
+
+        let uri = format!("/api/collections/{id}/token");
+        assert_eq!(client.post(&uri, "").dispatch().await.status(), Status::NoContent);
+        let token = current_token().await.unwrap();
+        assert_eq!(client.rss_status(&old_token).await, Status::Unauthorized);
+        assert_eq!(client.rss_status(&token).await, Status::Ok);
+
+        let uri = format!("/api/collections/{id}/token?grace_hours=1");
+        assert_eq!(client.post(&uri, "").dispatch().await.status(), Status::NoContent);
+        let new_token = current_token().await.unwrap();
+        assert_eq!(client.rss_status(&token).await, Status::Ok);
+        assert_eq!(client.rss_status(&new_token).await, Status::Ok);
+
+        sqlx::query("UPDATE collections SET previous_token_expires_at = datetime('now', '-1 minute') WHERE id = ?")
+            .bind(id)
+            .execute(&client.db)
+            .await
+            .unwrap();
+        assert_eq!(client.rss_status(&token).await, Status::Unauthorized);
+        assert_eq!(client.rss_status(&new_token).await, Status::Ok);
+    }
+
+    fn date(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    fn target() -> MergedFeedTarget {
+        MergedFeedTarget {
+            user_id: 1,
+            collection_id: None,
+            title: None,
+            description: None,
+            settings: UserSettings::default(),
+            changed_at: date("2022-05-20T00:00:00Z"),
+        }
+    }
+
+    fn stored_item(id: i64, updated_at: DateTime<Utc>) -> StoredItem {
+        StoredItem {
+            id,
+            data: String::new(),
+            updated_at,
+            source: EntrySource {
+                name: "a".to_owned(),
+                url: "https://example.com/rss".to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_merged_feed_etag() {
+        let updated_at = date("2022-05-24T12:00:00Z");
+        let items = [stored_item(1, updated_at), stored_item(2, updated_at)];
+        let etag = merged_feed_etag(&items, &[], &target(), OutputFormat::Rss);
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        assert_eq!(etag, merged_feed_etag(&items, &[], &target(), OutputFormat::Rss));
+
+        assert_ne!(etag, merged_feed_etag(&items, &[], &target(), OutputFormat::Atom));
+        assert_ne!(etag, merged_feed_etag(&items[..1], &[], &target(), OutputFormat::Rss));
+        let updated = [
+            stored_item(1, updated_at),
+            stored_item(2, updated_at + Duration::seconds(1)),
+        ];
+        assert_ne!(etag, merged_feed_etag(&updated, &[], &target(), OutputFormat::Rss));
+        assert_ne!(etag, merged_feed_etag(&items, &[3], &target(), OutputFormat::Rss));
+        let mut settings = target();
+        settings.settings.prefix_titles = true;
+        assert_ne!(etag, merged_feed_etag(&items, &[], &settings, OutputFormat::Rss));
+    }
+
+    #[test]
+    fn test_merged_feed_last_modified() {
+        let updated_at = date("2022-05-24T12:00:00Z");
+        let items = [
+            stored_item(1, updated_at - Duration::hours(1)),
+            stored_item(2, updated_at),
+        ];
+        assert_eq!(merged_feed_last_modified(&items, &[], &target()), Some(updated_at));
+        assert_eq!(
+            merged_feed_last_modified(&[], &[], &target()),
+            Some(target().changed_at)
+        );
+        // A new list of failed feeds must not be taken as unmodified by date
+        assert_eq!(merged_feed_last_modified(&items, &[3], &target()), None);
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        const ETAG: &str = "\"abc\"";
+        let last_modified = date("2022-05-24T12:00:00.500Z");
+        let conditions = |if_none_match, if_modified_since| Conditions {
+            if_none_match,
+            if_modified_since,
+        };
+        let is_not_modified = |if_none_match, if_modified_since| {
+            conditions(if_none_match, if_modified_since).is_not_modified(ETAG, Some(last_modified))
+        };
+
+        assert!(!is_not_modified(None, None));
+        assert!(is_not_modified(Some("\"abc\""), None));
+        assert!(!is_not_modified(Some("\"xyz\""), None));
+        assert!(is_not_modified(Some("*"), None));
+        assert!(is_not_modified(Some("\"xyz\", \"abc\""), None));
+        assert!(!is_not_modified(Some("\"xyz\", \"uvw\""), None));
+        assert!(is_not_modified(Some("W/\"abc\""), None));
+
+        // Last-Modified is sent in whole seconds
+        let since = http_date(last_modified);
+        assert!(is_not_modified(None, Some(&since)));
+        assert!(is_not_modified(None, Some("Tue, 24 May 2022 13:00:00 GMT")));
+        assert!(!is_not_modified(None, Some("Tue, 24 May 2022 11:59:59 GMT")));
+        assert!(!is_not_modified(None, Some("yesterday")));
+        assert!(!conditions(None, Some(&since)).is_not_modified(ETAG, None));
+
+        // If-None-Match takes precedence over If-Modified-Since
+        assert!(!is_not_modified(Some("\"xyz\""), Some(&since)));
+        assert!(is_not_modified(Some("\"abc\""), Some("Tue, 24 May 2022 11:59:59 GMT")));
+    }
+}
@@ -0,0 +1,389 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// Offsets of time zone abbreviations in minutes
+const NAMED_ZONES: &[(&str, i32)] = &[
+    ("UT", 0),
+    ("UTC", 0),
+    ("GMT", 0),
+    ("Z", 0),
+    ("WET", 0),
+    ("WEST", 60),
+    ("BST", 60),
+    ("CET", 60),
+    ("CEST", 120),
+    ("EET", 120),
+    ("EEST", 180),
+    ("MSK", 180),
+    ("SGT", 480),
+    ("HKT", 480),
+    ("JST", 540),
+    ("KST", 540),
+    ("AEST", 600),
+    ("AEDT", 660),
+    ("EST", -300),
+    ("EDT", -240),
+    ("CDT", -300),
+    ("MST", -420),
+    ("MDT", -360),
+    ("PST", -480),
+    ("PDT", -420),
+];
+
+/// Abbreviations of several time zones, e.g. `CST` is US Central in RFC 2822
+/// but China Standard Time in Chinese feeds. Dates with them are taken as local
+/// time of the feed's timezone.
+const AMBIGUOUS_ZONES: &[&str] = &["CST", "IST"];
+
+/// Month names and abbreviations in English, French, German, Spanish,
+/// Italian, Portuguese and Dutch, in lowercase
+const MONTHS: &[(&str, u32)] = &[
+    ("jan", 1),
+    ("january", 1),
+    ("janv", 1),
+    ("janvier", 1),
+    ("januar", 1),
+    ("jän", 1),
+    ("januari", 1),
+    ("ene", 1),
+    ("enero", 1),
+    ("gen", 1),
+    ("gennaio", 1),
+    ("janeiro", 1),
+    ("feb", 2),
+    ("february", 2),
+    ("févr", 2),
+    ("fév", 2),
+    ("février", 2),
+    ("februar", 2),
+    ("februari", 2),
+    ("febrero", 2),
+    ("febbraio", 2),
+    ("fev", 2),
+    ("fevereiro", 2),
+    ("mar", 3),
+    ("march", 3),
+    ("mars", 3),
+    ("mär", 3),
+    ("märz", 3),
+    ("maart", 3),
+    ("mrt", 3),
+    ("marzo", 3),
+    ("março", 3),
+    ("apr", 4),
+    ("april", 4),
+    ("avr", 4),
+    ("avril", 4),
+    ("abr", 4),
+    ("abril", 4),
+    ("aprile", 4),
+    ("may", 5),
+    ("mai", 5),
+    ("mei", 5),
+    ("mayo", 5),
+    ("mag", 5),
+    ("maggio", 5),
+    ("maio", 5),
+    ("jun", 6),
+    ("june", 6),
+    ("juin", 6),
+    ("juni", 6),
+    ("junio", 6),
+    ("giu", 6),
+    ("giugno", 6),
+    ("junho", 6),
+    ("jul", 7),
+    ("july", 7),
+    ("juil", 7),
+    ("juillet", 7),
+    ("juli", 7),
+    ("julio", 7),
+    ("lug", 7),
+    ("luglio", 7),
+    ("julho", 7),
+    ("aug", 8),
+    ("august", 8),
+    ("août", 8),
+    ("augustus", 8),
+    ("ago", 8),
+    ("agosto", 8),
+    ("sep", 9),
+    ("sept", 9),
+    ("september", 9),
+    ("septembre", 9),
+    ("septiembre", 9),
+    ("setiembre", 9),
+    ("set", 9),
+    ("settembre", 9),
+    ("setembro", 9),
+    ("oct", 10),
+    ("october", 10),
+    ("octobre", 10),
+    ("okt", 10),
+    ("oktober", 10),
+    ("octubre", 10),
+    ("ott", 10),
+    ("ottobre", 10),
+    ("out", 10),
+    ("outubro", 10),
+    ("nov", 11),
+    ("november", 11),
+    ("novembre", 11),
+    ("noviembre", 11),
+    ("novembro", 11),
+    ("dec", 12),
+    ("december", 12),
+    ("déc", 12),
+    ("décembre", 12),
+    ("dez", 12),
+    ("dezember", 12),
+    ("dic", 12),
+    ("diciembre", 12),
+    ("dicembre", 12),
+    ("dezembro", 12),
+];
+
+/// Parses a date of a feed item, trying the standard formats first and then
+/// the variants seen in the wild. Dates without an offset are taken as local
+/// time of `timezone`.
+pub fn parse_date(text: &str, timezone: Tz) -> Option<DateTime<Utc>> {
+    let text = strip_ambiguous_zone(text.trim());
+    if let Ok(date) = DateTime::parse_from_rfc2822(text) {
+        return Some(date.into());
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.into());
+    }
+    parse_numeric_date(text, timezone).or_else(|| parse_named_month_date(text, timezone))
+}
+
+/// Drops a trailing abbreviation of [`AMBIGUOUS_ZONES`], e.g. `22:18 CST` or
+/// `+0800 (CST)`
+fn strip_ambiguous_zone(text: &str) -> &str {
+    match text.rsplit_once(char::is_whitespace) {
+        Some((rest, zone)) if AMBIGUOUS_ZONES.contains(&zone.trim_matches(['(', ')']).to_uppercase().as_str()) => {
+            rest.trim_end()
+        }
+        _ => text,
+    }
+}
+
+/// Parses dates starting with a numeric year, e.g. `2022-05-23T22:18:57.333`,
+/// `2022/05/23 22:18 +0800`, `2022-05-23` or `2022年5月23日 22:18`
+fn parse_numeric_date(text: &str, timezone: Tz) -> Option<DateTime<Utc>> {
+    if !text.get(..4)?.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let text = text.replace(['/', '年', '月'], "-").replace('日', " ");
+    let (date, rest) = text.split_once(['T', ' ']).unwrap_or((&text, ""));
+    let date = NaiveDate::parse_from_str(date.trim_end_matches('-'), "%Y-%m-%d").ok()?;
+    let (time, offset) = parse_time_and_offset(rest.trim())?;
+    resolve(date.and_time(time.unwrap_or_else(midnight)), offset, timezone)
+}
+
+/// Parses dates with the name of the month, e.g. `Mon, 23 May 2022 22:18:57
+/// EST`, `23 mai 2022 22:18`, `May 23rd, 2022 10:18 PM` or `23 May 22`
+fn parse_named_month_date(text: &str, timezone: Tz) -> Option<DateTime<Utc>> {
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    let mut time = None;
+    let mut offset = None;
+    let mut pm = None;
+    let tokens = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty());
+    for token in tokens {
+        let word = token.trim_matches(|c| c == '(' || c == ')' || c == '.').to_lowercase();
+        if token.starts_with(|c: char| c.is_ascii_digit()) && token.contains(':') {
+            let (t, o) = parse_time_and_offset(token)?;
+            time = t;
+            offset = o.or(offset);
+        } else if token.starts_with(['+', '-']) {
+            offset = Some(parse_offset(token)?);
+        } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+            // Ordinal suffixes are ignored, e.g. `23rd`
+            let digits = token.trim_end_matches(|c: char| !c.is_ascii_digit());
+            let n: i32 = digits.parse().ok()?;
+            if digits.len() > 2 || day.is_some() {
+                year = Some(if digits.len() > 2 { n } else { expand_year(n) });
+            } else {
+                day = Some(n as u32);
+            }
+        } else if let Some(m) = month_number(&word) {
+            month = Some(m);
+        } else if word == "am" || word == "pm" {
+            pm = Some(word == "pm");
+        } else if let Some(o) = parse_named_offset(&word.to_uppercase()) {
+            // An explicit numeric offset wins over a trailing comment, e.g.
+            // `+0800 (CST)`
+            offset = offset.or(Some(o));
+        }
+        // Other words such as weekdays are ignored
+    }
+
+    let date = NaiveDate::from_ymd_opt(year?, month?, day?)?;
+    let mut time = time.unwrap_or_else(midnight);
+    if let Some(pm) = pm {
+        let hour = time.hour() % 12 + if pm { 12 } else { 0 };
+        time = time.with_hour(hour)?;
+    }
+    resolve(date.and_time(time), offset, timezone)
+}
+
+/// Parses the part after the date, e.g. `22:18:57.333+08:00`, `22:18 CST` or
+/// nothing. Returns `None` if it is malformed.
+fn parse_time_and_offset(text: &str) -> Option<(Option<NaiveTime>, Option<FixedOffset>)> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == ':' || c == '.'))
+        .unwrap_or(text.len());
+    let (time, offset) = text.split_at(end);
+    let time = match time {
+        "" => None,
+        time => Some(
+            NaiveTime::parse_from_str(time, "%H:%M:%S%.f")
+                .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+                .ok()?,
+        ),
+    };
+    let offset = match offset.trim() {
+        "" => None,
+        offset => Some(parse_offset(offset)?),
+    };
+    Some((time, offset))
+}
+
+/// Parses `Z`, `+08:00`, `+0800`, `+8`, `EST`, `GMT+8`, etc.
+fn parse_offset(text: &str) -> Option<FixedOffset> {
+    let text = text.trim_matches(|c| c == '(' || c == ')').to_uppercase();
+    if let Some(offset) = parse_named_offset(&text) {
+        return Some(offset);
+    }
+    let numeric = text
+        .strip_prefix("GMT")
+        .or_else(|| text.strip_prefix("UTC"))
+        .unwrap_or(&text);
+    let (sign, digits) = if let Some(digits) = numeric.strip_prefix('+') {
+        (1, digits)
+    } else {
+        (-1, numeric.strip_prefix('-')?)
+    };
+    let digits = digits.replace(':', "");
+    if digits.is_empty() || digits.len() > 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        n => (
+            digits[..n - 2].parse::<i32>().ok()?,
+            digits[n - 2..].parse::<i32>().ok()?,
+        ),
+    };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn parse_named_offset(name: &str) -> Option<FixedOffset> {
+    NAMED_ZONES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .and_then(|(_, minutes)| FixedOffset::east_opt(minutes * 60))
+}
+
+fn month_number(word: &str) -> Option<u32> {
+    MONTHS.iter().find(|(name, _)| *name == word).map(|(_, month)| *month)
+}
+
+fn midnight() -> NaiveTime {
+    NaiveTime::from_hms_opt(0, 0, 0).unwrap()
+}
+
+/// Expands a two-digit year as RFC 2822 does
+fn expand_year(year: i32) -> i32 {
+    match year {
+        0..=49 => year + 2000,
+        50..=99 => year + 1900,
+        _ => year,
+    }
+}
+
+/// Converts local `time` with `offset`, or in `timezone` if there is no offset
+fn resolve(time: NaiveDateTime, offset: Option<FixedOffset>, timezone: Tz) -> Option<DateTime<Utc>> {
+    match offset {
+        Some(offset) => offset.from_local_datetime(&time).single().map(|t| t.into()),
+        // The earlier one is taken for the repeated hour when DST ends
+        None => timezone
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date() {
+        let cases = [
+            ("Mon, 23 May 2022 14:18:57 +0000", "2022-05-23T14:18:57Z"),
+            ("23 May 2022 14:18:57 GMT", "2022-05-23T14:18:57Z"),
+            ("Mon, 23 May 2022 10:18:57 EDT", "2022-05-23T14:18:57Z"),
+            ("Monday, 23 May 2022 22:18:57 +0800 (CST)", "2022-05-23T14:18:57Z"),
+            ("Mon, 23 May 22 14:18:57 Z", "2022-05-23T14:18:57Z"),
+            ("2022-05-23T22:18:57+08:00", "2022-05-23T14:18:57Z"),
+            ("2022-05-23T14:18:57.333Z", "2022-05-23T14:18:57.333Z"),
+            ("2022-05-23 22:18:57 +0800", "2022-05-23T14:18:57Z"),
+            ("2022/05/23 14:18:57 UTC", "2022-05-23T14:18:57Z"),
+            ("2022-05-23T14:18:57", "2022-05-23T14:18:57Z"),
+            ("2022-05-23", "2022-05-23T00:00:00Z"),
+            ("2022年5月23日 22:18 GMT+8", "2022-05-23T14:18:00Z"),
+            ("May 23rd, 2022 2:18 PM", "2022-05-23T14:18:00Z"),
+            ("lun. 23 mai 2022 16:18:57 CEST", "2022-05-23T14:18:57Z"),
+            ("Di, 23. Dez. 2022 15:18:57 +0100", "2022-12-23T14:18:57Z"),
+            ("23 de agosto de 2022 14:18", "2022-08-23T14:18:00Z"),
+        ];
+        for (text, expected) in cases {
+            let expected = DateTime::parse_from_rfc3339(expected).unwrap();
+            assert_eq!(parse_date(text, Tz::UTC), Some(expected.into()), "{text}");
+        }
+
+        for text in [
+            "",
+            "yesterday",
+            "2022-13-01",
+            "32 May 2022",
+            "23 May",
+            "Mon, 23 May 2022 25:00:00 GMT",
+            "2022-05-23 22:18 ()",
+            "2022-05-23 22:18 北京时间",
+        ] {
+            assert_eq!(parse_date(text, Tz::UTC), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_parse_date_with_timezone() {
+        let expected = DateTime::parse_from_rfc3339("2022-05-23T14:18:57Z").unwrap();
+        let parse = |text| parse_date(text, Tz::Asia__Shanghai);
+        assert_eq!(parse("2022-05-23T22:18:57"), Some(expected.into()));
+        assert_eq!(parse("23 May 2022 22:18:57"), Some(expected.into()));
+        // Explicit offsets are kept
+        assert_eq!(parse("2022-05-23T14:18:57Z"), Some(expected.into()));
+        // Ambiguous abbreviations follow the timezone of the feed
+        assert_eq!(parse("Mon, 23 May 2022 22:18:57 CST"), Some(expected.into()));
+        assert_eq!(parse("2022-05-23 22:18:57 CST"), Some(expected.into()));
+        assert_eq!(parse("Mon, 23 May 2022 22:18:57 +0800 (CST)"), Some(expected.into()));
+        let expected_utc = DateTime::parse_from_rfc3339("2022-05-23T22:18:57Z").unwrap();
+        assert_eq!(
+            parse_date("Mon, 23 May 2022 22:18:57 CST", Tz::UTC),
+            Some(expected_utc.into())
+        );
+
+        // Daylight saving time
+        let expected = DateTime::parse_from_rfc3339("2022-07-01T16:00:00Z").unwrap();
+        assert_eq!(
+            parse_date("2022-07-01 12:00", Tz::America__New_York),
+            Some(expected.into())
+        );
+    }
+}
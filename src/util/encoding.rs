@@ -0,0 +1,121 @@
+use std::borrow::Cow;
+
+use encoding_rs::{Encoding, UTF_8};
+
+/// Transcodes a feed document into UTF-8, which is the only encoding the XML
+/// parser understands. Following RFC 7303, the encoding is taken from the
+/// BOM, then `charset` of the `Content-Type`, then the XML declaration. As
+/// servers often send a wrong charset, the first one that decodes `data`
+/// without errors is used, except that valid UTF-8 declared as such (or not
+/// declared at all) is kept as is.
+///
+/// The `encoding` of the XML declaration is rewritten to `UTF-8` accordingly.
+pub fn decode_document<'a>(data: &'a [u8], charset: Option<&str>) -> Cow<'a, [u8]> {
+    let (bom_encoding, data) = match Encoding::for_bom(data) {
+        Some((encoding, bom_len)) => (Some(encoding), &data[bom_len..]),
+        None => (None, data),
+    };
+    let declared = declared_encoding(data);
+    // Single-byte encodings like `ISO-8859-1` decode anything without errors,
+    // so a wrong charset of them would garble UTF-8 documents
+    if bom_encoding.map_or(true, |e| e == UTF_8)
+        && declared.map_or(true, is_utf8_label)
+        && std::str::from_utf8(data).is_ok()
+    {
+        return Cow::Borrowed(data);
+    }
+    let candidates: Vec<&'static Encoding> = [
+        bom_encoding,
+        charset.and_then(|c| Encoding::for_label(c.as_bytes())),
+        declared.and_then(|c| Encoding::for_label(c.as_bytes())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    // Documents in UTF-8 are passed through as they are
+    let encoding = candidates
+        .iter()
+        .copied()
+        .find(|e| e.decode_without_bom_handling_and_without_replacement(data).is_some())
+        .or_else(|| candidates.first().copied())
+        .unwrap_or(UTF_8);
+    if encoding == UTF_8 && declared.map_or(true, is_utf8_label) {
+        return Cow::Borrowed(data);
+    }
+
+    let (text, _) = encoding.decode_without_bom_handling(data);
+    let text = match declared {
+        Some(label) => text.replacen(label, "UTF-8", 1),
+        None => text.into_owned(),
+    };
+    Cow::Owned(text.into_bytes())
+}
+
+/// Extracts `charset` from a `Content-Type` header, e.g. `GBK` of `text/xml;
+/// charset="GBK"`
+pub fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Returns `encoding` of the XML declaration, e.g. `GB2312` of `<?xml
+/// version="1.0" encoding="GB2312"?>`. The declaration is ASCII-compatible in
+/// all encodings concerned.
+fn declared_encoding(data: &[u8]) -> Option<&str> {
+    let data = data.strip_prefix(b"<?xml")?;
+    let end = data.windows(2).position(|w| w == b"?>")?;
+    let declaration = std::str::from_utf8(&data[..end]).ok()?;
+    let value = declaration
+        .split_once("encoding")?
+        .1
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    value[1..].split(quote).next()
+}
+
+fn is_utf8_label(label: &str) -> bool {
+    label.eq_ignore_ascii_case("utf-8") || label.eq_ignore_ascii_case("utf8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_document() {
+        let xml = r#"<?xml version="1.0" encoding="Shift_JIS"?><title>ニュース</title>"#;
+        let (data, _, _) = encoding_rs::SHIFT_JIS.encode(xml);
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?><title>ニュース</title>"#;
+        assert_eq!(decode_document(&data, None), expected.as_bytes());
+        // A wrong charset of the server is overridden
+        assert_eq!(decode_document(&data, Some("utf-8")), expected.as_bytes());
+        let xml = "<title>Actualités</title>";
+        assert_eq!(decode_document(xml.as_bytes(), Some("iso-8859-1")), xml.as_bytes());
+
+        // The charset is used without any declaration
+        let (data, _, _) = encoding_rs::BIG5.encode("<title>新聞</title>");
+        assert_eq!(decode_document(&data, Some("big5")), "<title>新聞</title>".as_bytes());
+
+        // BOM
+        let mut data = vec![0xFF, 0xFE];
+        data.extend("<title>新闻</title>".encode_utf16().flat_map(|c| c.to_le_bytes()));
+        assert_eq!(decode_document(&data, Some("gbk")), "<title>新闻</title>".as_bytes());
+
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?><title>新闻</title>"#;
+        assert!(matches!(decode_document(xml.as_bytes(), None), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_charset_param() {
+        assert_eq!(charset_param("text/xml; charset=GBK"), Some("GBK"));
+        assert_eq!(charset_param("application/rss+xml;Charset=\"utf-8\""), Some("utf-8"));
+        assert_eq!(charset_param("text/xml"), None);
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use xmltree::Element;
+
+use crate::model::FeedItem;
+
+/// One item of a feed in a format-neutral representation, which can be written
+/// out as any of the supported output formats
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FeedEntry {
+    pub title: String,
+
+    pub link: String,
+
+    /// Unique identifier of the item, e.g. `<guid>` of RSS or `<id>` of Atom
+    pub guid: Option<String>,
+
+    pub pub_date: Option<DateTime<Utc>>,
+
+    /// Summary of the item, may contain HTML
+    pub description: Option<String>,
+
+    /// Full content of the item, may contain HTML
+    pub content: Option<String>,
+
+    pub author: Option<String>,
+
+    pub categories: Vec<String>,
+
+    /// Link to the comments page of the item, e.g. `<comments>` of RSS or
+    /// `<link rel="replies">` of Atom
+    pub comments: Option<String>,
+
+    pub enclosures: Vec<Enclosure>,
+
+    /// Elements unknown to the model (e.g. `<media:thumbnail>`, or `<torrent>`
+    /// of mikanani.me), which are copied as-is into RSS output
+    #[serde(default, with = "xml_elements")]
+    pub extensions: Vec<Element>,
+}
+
+/// Attached file of an item, e.g. a podcast episode or a torrent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Enclosure {
+    pub url: String,
+
+    pub length: Option<u64>,
+
+    pub mime_type: Option<String>,
+}
+
+impl From<FeedEntry> for FeedItem {
+    fn from(entry: FeedEntry) -> Self {
+        FeedItem {
+            title: entry.title,
+            link: entry.link,
+            guid: entry.guid,
+            pub_date: entry.pub_date,
+            description: entry.description,
+            content: entry.content,
+            author: entry.author,
+            categories: entry.categories,
+            comments: entry.comments,
+        }
+    }
+}
+
+/// (De)serializes XML elements as a list of XML texts
+mod xml_elements {
+    use rocket::serde::de::Error as _;
+    use rocket::serde::ser::Error as _;
+    use rocket::serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use xmltree::{Element, EmitterConfig};
+
+    pub fn serialize<S: Serializer>(elements: &[Element], serializer: S) -> Result<S::Ok, S::Error> {
+        let config = EmitterConfig::new().write_document_declaration(false);
+        let texts = elements
+            .iter()
+            .map(|e| {
+                let mut buf = Vec::new();
+                e.write_with_config(&mut buf, config.clone())
+                    .map_err(S::Error::custom)?;
+                String::from_utf8(buf).map_err(S::Error::custom)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        texts.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Element>, D::Error> {
+        let texts = Vec::<String>::deserialize(deserializer)?;
+        texts
+            .iter()
+            .map(|t| Element::parse(t.as_bytes()).map_err(D::Error::custom))
+            .collect()
+    }
+}
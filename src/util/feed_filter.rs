@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+
+use regex::{Regex, RegexBuilder};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::InvalidFilterError;
+use crate::model::{FilterField, SourceFeed};
+use crate::util::feed_entry::FeedEntry;
+use crate::util::keyword_query::KeywordQuery;
+
+/// Filter of the entries of a source feed, parsed from its settings
+#[derive(Debug, Clone, Default)]
+pub struct FeedFilter {
+    /// Rules which must all pass, including the ones on titles built from
+    /// `keywords` and `regex` of the feed
+    rules: Vec<Rule>,
+
+    normalizer: Normalizer,
+}
+
+/// Transformation applied to both keywords and fields before matching
+#[derive(Debug, Clone, Copy, Default)]
+struct Normalizer {
+    ignore_case: bool,
+
+    nfkc: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    field: FilterField,
+
+    matcher: Matcher,
+
+    exclude: bool,
+
+    /// Whether items lacking `field` pass
+    keep_missing: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Keywords(KeywordQuery),
+    Regex(Regex),
+}
+
+impl FeedFilter {
+    pub fn new(feed: &SourceFeed) -> Result<Self, InvalidFilterError> {
+        let normalizer = Normalizer {
+            ignore_case: feed.ignore_case,
+            nfkc: feed.normalize,
+        };
+        let parse_keywords = |keywords: &str| {
+            KeywordQuery::parse(keywords).map(|query| query.map(|q| q.map_terms(&|t| normalizer.apply(t).into_owned())))
+        };
+
+        let mut rules = Vec::new();
+        let title_query = if feed.legacy_keywords {
+            KeywordQuery::parse_legacy(&feed.keywords).map(|q| q.map_terms(&|t| normalizer.apply(t).into_owned()))
+        } else {
+            parse_keywords(&feed.keywords)?
+        };
+        if let Some(query) = title_query {
+            rules.push(Rule::title(Matcher::Keywords(query)));
+        }
+        match feed.regex.as_deref() {
+            // The regex is matched against normalized text, but the pattern itself is
+            // left as-is so as not to break escapes or classes
+            Some(pattern) if !pattern.is_empty() => {
+                let regex = RegexBuilder::new(pattern).case_insensitive(feed.ignore_case).build()?;
+                rules.push(Rule::title(Matcher::Regex(regex)));
+            }
+            _ => {}
+        }
+        for (i, rule) in feed.rules.iter().enumerate() {
+            let query = parse_keywords(&rule.keywords)?.ok_or(InvalidFilterError::EmptyRule(i + 1))?;
+            rules.push(Rule {
+                field: rule.field,
+                matcher: Matcher::Keywords(query),
+                exclude: rule.exclude,
+                keep_missing: rule.keep_missing.unwrap_or(rule.exclude),
+            });
+        }
+        Ok(Self { rules, normalizer })
+    }
+
+    pub fn matches(&self, entry: &FeedEntry) -> bool {
+        self.rules.iter().all(|rule| rule.matches(entry, self.normalizer))
+    }
+}
+
+impl Normalizer {
+    fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        if self.nfkc {
+            text = Cow::Owned(text.nfkc().collect());
+        }
+        if self.ignore_case {
+            text = Cow::Owned(text.to_lowercase());
+        }
+        text
+    }
+}
+
+impl Rule {
+    /// Inclusive rule on titles, which drops items without a title
+    fn title(matcher: Matcher) -> Self {
+        Self {
+            field: FilterField::Title,
+            matcher,
+            exclude: false,
+            keep_missing: false,
+        }
+    }
+
+    fn matches(&self, entry: &FeedEntry, normalizer: Normalizer) -> bool {
+        let values = field_values(entry, self.field);
+        if values.is_empty() {
+            return self.keep_missing;
+        }
+        let hit = values.iter().map(|v| normalizer.apply(v)).any(|v| match &self.matcher {
+            Matcher::Keywords(query) => query.matches(&v),
+            Matcher::Regex(regex) => regex.is_match(&v),
+        });
+        hit != self.exclude
+    }
+}
+
+/// Non-empty values of `field` in `entry`
+fn field_values(entry: &FeedEntry, field: FilterField) -> Vec<&str> {
+    let values: Vec<&str> = match field {
+        FilterField::Title => vec![&entry.title],
+        FilterField::Description => [&entry.description, &entry.content]
+            .into_iter()
+            .flatten()
+            .map(|s| s.as_str())
+            .collect(),
+        FilterField::Category => entry.categories.iter().map(|s| s.as_str()).collect(),
+        FilterField::Author => entry.author.iter().map(|s| s.as_str()).collect(),
+    };
+    values.into_iter().filter(|v| !v.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::FilterRule;
+    use crate::util::feed_parser::FeedDocument;
+
+    fn source_feed(keywords: &str, regex: Option<&str>) -> SourceFeed {
+        SourceFeed {
+            id: None,
+            name: String::new(),
+            url: String::new(),
+            keywords: keywords.to_owned(),
+            regex: regex.map(|r| r.to_owned()),
+            rules: Vec::new(),
+            ignore_case: false,
+            normalize: false,
+            timezone: None,
+            legacy_keywords: false,
+        }
+    }
+
+    fn entry(title: &str) -> FeedEntry {
+        FeedEntry {
+            title: title.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_regex() {
+        let filter = FeedFilter::new(&source_feed("1080p", Some(r"\[(0[5-9]|1\d)\]"))).unwrap();
+        assert!(filter.matches(&entry("[Lilith-Raws] Spy x Family - [07][1080p]")));
+        assert!(!filter.matches(&entry("[Lilith-Raws] Spy x Family - [04][1080p]")));
+        assert!(!filter.matches(&entry("[Lilith-Raws] Spy x Family - [07][720p]")));
+
+        let filter = FeedFilter::new(&source_feed("", Some(""))).unwrap();
+        assert!(filter.matches(&entry("anything")));
+
+        assert!(FeedFilter::new(&source_feed("", Some(r"\[(0[5-9]"))).is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        let rule = |field, keywords: &str, exclude, keep_missing| FilterRule {
+            field,
+            keywords: keywords.to_owned(),
+            exclude,
+            keep_missing,
+        };
+        let mut feed = source_feed("", None);
+        feed.rules = vec![
+            rule(FilterField::Category, "sponsored", true, None),
+            rule(FilterField::Author, "alice OR bob", false, Some(true)),
+            rule(FilterField::Description, "rust", false, None),
+        ];
+        let filter = FeedFilter::new(&feed).unwrap();
+
+        let item = FeedEntry {
+            description: Some("<p>rust 1.61</p>".to_owned()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&item));
+        assert!(!filter.matches(&FeedEntry {
+            categories: vec!["news".to_owned(), "sponsored".to_owned()],
+            ..item.clone()
+        }));
+        assert!(filter.matches(&FeedEntry {
+            categories: vec!["news".to_owned()],
+            author: Some("bob".to_owned()),
+            ..item.clone()
+        }));
+        assert!(!filter.matches(&FeedEntry {
+            author: Some("carol".to_owned()),
+            ..item.clone()
+        }));
+        assert!(!filter.matches(&FeedEntry {
+            description: None,
+            ..item.clone()
+        }));
+
+        // Items without a title fail the keywords on titles
+        let filter = FeedFilter::new(&source_feed("-sponsored", None)).unwrap();
+        assert!(filter.matches(&entry("rust")));
+        assert!(!filter.matches(&entry("")));
+
+        feed.rules = vec![rule(FilterField::Title, " ", false, None)];
+        assert_eq!(
+            FeedFilter::new(&feed).unwrap_err().to_string(),
+            "rule 1 has no keywords"
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let data = std::fs::read("./src/tests/data/11.xml").unwrap();
+        let titles = |feed: &SourceFeed| -> Vec<String> {
+            let doc = FeedDocument::parse(&data).unwrap();
+            let doc = doc.with_filter(FeedFilter::new(feed).unwrap());
+            doc.into_entries().unwrap().into_iter().map(|e| e.title).collect()
+        };
+
+        let mut feed = source_feed("spy 1080", None);
+        assert_eq!(titles(&feed).len(), 0);
+
+        feed.ignore_case = true;
+        assert_eq!(
+            titles(&feed),
+            ["[Lilith-Raws] Spy x Family - 07 [Baha][WEB-DL][1080p][AVC AAC][CHT][MP4]"]
+        );
+
+        feed.normalize = true;
+        assert_eq!(titles(&feed).len(), 3);
+
+        feed.keywords = "１０８０Ｐ".to_owned();
+        feed.regex = Some(r"\[1080P\]".to_owned());
+        assert_eq!(
+            titles(&feed),
+            [
+                "【喵萌奶茶屋】★04月新番★[间谍过家家 / SPY×FAMILY][07][１０８０Ｐ][简日双语][招募翻译]",
+                "[Lilith-Raws] Spy x Family - 07 [Baha][WEB-DL][1080p][AVC AAC][CHT][MP4]",
+            ]
+        );
+    }
+}
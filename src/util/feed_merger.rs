@@ -1,83 +1,884 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
-use xmltree::{Element, XMLNode};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::Url;
+use rocket::serde::json::serde_json;
+use unicode_normalization::UnicodeNormalization;
+use xmltree::{Element, Namespace, XMLNode};
+
+use crate::model::OutputFormat;
+use crate::util::feed_entry::FeedEntry;
+use crate::util::feed_parser::{text_node, ATOM_NAMESPACE, CONTENT_NAMESPACE};
+use crate::util::json_feed::{self, JsonFeed, JsonFeedItem, JsonFeedItemExtension};
+
+/// Title of the merged feed unless specified
+const DEFAULT_TITLE: &str = "MIOR";
 
-use crate::error::Error;
-use crate::util::feed_parser::FeedDocument;
+/// Description of the merged feed unless specified, as it is required by RSS
+/// 2.0
+const DEFAULT_DESCRIPTION: &str = "Merged by MIOR";
 
-type Result<T> = std::result::Result<T, Error>;
+const GENERATOR: &str = "MIOR";
+
+/// Atom id of the merged feed unless specified, which isn't unique
+const DEFAULT_ID: &str = "urn:mior:merged";
+
+/// Source feed of merged entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntrySource {
+    pub name: String,
+
+    pub url: String,
+}
 
 pub struct FeedMerger {
-    elements: Vec<(XMLNode, Option<DateTime<Utc>>)>,
+    entries: Vec<MergedEntry>,
+
+    /// Permanent and globally unique id of the merged feed, e.g.
+    /// `tag:example.com,2022:user/1`, which Atom requires
+    id: Option<String>,
+
+    title: String,
+
+    description: Option<String>,
+
+    /// URL at which the merged feed is served, e.g. `https://example.com/rss?token=xxx`
+    self_link: Option<String>,
+
+    /// Minutes for which readers may cache the merged feed
+    ttl: Option<u32>,
+
+    /// Also treats entries with the same normalized title as duplicates
+    dedup_titles: bool,
+
+    /// Prefixes titles of entries with the name of their source, e.g.
+    /// `[Rust Blog] Announcing Rust 1.61.0`
+    prefix_titles: bool,
+}
+
+/// Entry along with all the sources which carried it
+struct MergedEntry {
+    entry: FeedEntry,
+
+    sources: Vec<EntrySource>,
+}
+
+/// Key identifying duplicated entries
+#[derive(PartialEq, Eq, Hash)]
+enum DedupKey {
+    Guid(String),
+
+    /// Link of an entry without a guid
+    Link(String),
+
+    /// Link of an entry with a guid
+    LinkWithGuid(String),
+
+    Title(String),
 }
 
 impl FeedMerger {
     pub fn new() -> Self {
-        Self { elements: Vec::new() }
+        Self {
+            entries: Vec::new(),
+            id: None,
+            title: DEFAULT_TITLE.to_owned(),
+            description: None,
+            self_link: None,
+            ttl: None,
+            dedup_titles: false,
+            prefix_titles: false,
+        }
+    }
+
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn with_title(mut self, title: String) -> Self {
+        self.title = title;
+        self
+    }
+
+    /// Sets the description of the channel. An empty one is omitted.
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description).filter(|d| !d.is_empty());
+        self
+    }
+
+    /// Sets the URL of the merged feed itself, from which the link to the
+    /// site is also derived
+    pub fn with_self_link(mut self, self_link: String) -> Self {
+        self.self_link = Some(self_link);
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
     }
 
-    /// Append all feed items in `doc`
-    pub fn append(&mut self, doc: FeedDocument) -> Result<()> {
-        let mut item_nodes = doc.into_item_nodes()?;
-        self.elements.append(&mut item_nodes);
-        Ok(())
+    pub fn with_dedup_titles(mut self, dedup_titles: bool) -> Self {
+        self.dedup_titles = dedup_titles;
+        self
     }
 
-    pub fn build(mut self) -> Vec<u8> {
-        // Sort the elements from most recent to least
-        self.elements.sort_by_key(|e| e.1);
-        self.elements.reverse();
+    pub fn with_prefix_titles(mut self, prefix_titles: bool) -> Self {
+        self.prefix_titles = prefix_titles;
+        self
+    }
+
+    /// Append feed items of `source`, e.g. entries of a `FeedDocument` or
+    /// items loaded from the database
+    pub fn append(&mut self, source: &EntrySource, entries: Vec<FeedEntry>) {
+        self.entries.extend(entries.into_iter().map(|entry| MergedEntry {
+            entry,
+            sources: vec![source.clone()],
+        }));
+    }
+
+    pub fn build(mut self, format: OutputFormat) -> Vec<u8> {
+        self.dedup();
+        if self.prefix_titles {
+            for merged in &mut self.entries {
+                // Title-less entries (e.g. Mastodon posts) are left as they are
+                if !merged.entry.title.is_empty() {
+                    merged.entry.title = format!("[{}] {}", merged.sources[0].name, merged.entry.title);
+                }
+            }
+        }
+
+        // Sort the entries from most recent to least
+        self.entries.sort_by_key(|e| e.entry.pub_date);
+        self.entries.reverse();
 
-        // Build the element tree
+        match format {
+            OutputFormat::Rss => self.build_rss(),
+            OutputFormat::Atom => self.build_atom(),
+            OutputFormat::Json => self.build_json(),
+        }
+    }
+
+    /// Merges entries with the same guid (or link, for entries without one) or
+    /// (optionally) title into the earliest published one
+    fn dedup(&mut self) {
+        // Entries without a date go last, so that dated copies are preferred
+        self.entries
+            .sort_by_key(|e| (e.entry.pub_date.is_none(), e.entry.pub_date));
+
+        let mut entries: Vec<MergedEntry> = Vec::with_capacity(self.entries.len());
+        let mut indices: HashMap<DedupKey, usize> = HashMap::new();
+        for merged in std::mem::take(&mut self.entries) {
+            let (keys, lookups) = self.dedup_keys(&merged.entry);
+            match lookups.iter().find_map(|key| indices.get(key)) {
+                Some(&i) => {
+                    let kept = &mut entries[i];
+                    for source in merged.sources {
+                        if !kept.sources.contains(&source) {
+                            kept.sources.push(source);
+                        }
+                    }
+                }
+                None => {
+                    for key in keys {
+                        indices.entry(key).or_insert(entries.len());
+                    }
+                    entries.push(merged);
+                }
+            }
+        }
+        self.entries = entries;
+    }
+
+    /// Returns the keys `entry` is indexed by, and the ones it looks up earlier
+    /// entries by. Guids are compared first, and links only if either entry
+    /// has no guid, since items with different guids may well link to the
+    /// same page.
+    fn dedup_keys(&self, entry: &FeedEntry) -> (Vec<DedupKey>, Vec<DedupKey>) {
+        let link = normalize_link(&entry.link);
+        let (mut keys, mut lookups) = match global_guid(entry) {
+            Some(guid) => (
+                vec![DedupKey::Guid(guid.clone()), DedupKey::LinkWithGuid(link.clone())],
+                vec![DedupKey::Guid(guid.clone()), DedupKey::Link(link)],
+            ),
+            None => (
+                vec![DedupKey::Link(link.clone())],
+                vec![DedupKey::Link(link.clone()), DedupKey::LinkWithGuid(link)],
+            ),
+        };
+        if self.dedup_titles {
+            let title = normalize_title(&entry.title);
+            if !title.is_empty() {
+                keys.push(DedupKey::Title(title.clone()));
+                lookups.push(DedupKey::Title(title));
+            }
+        }
+        (keys, lookups)
+    }
+
+    /// Time of the latest entry, which is regarded as the last change of the
+    /// merged feed
+    fn updated(&self) -> DateTime<Utc> {
+        self.entries
+            .iter()
+            .flat_map(|e| e.entry.pub_date)
+            .max()
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// Root URL of the site serving the merged feed
+    fn home_link(&self) -> Option<String> {
+        let self_link = Url::parse(self.self_link.as_ref()?).ok()?;
+        self_link.join("/").ok().map(String::from)
+    }
+
+    fn build_rss(self) -> Vec<u8> {
         let mut root_node = Element::new("rss");
+        root_node.attributes.insert("version".to_owned(), "2.0".to_owned());
+        let mut namespaces = Namespace::empty();
+        if self.entries.iter().any(|e| e.entry.content.is_some()) {
+            namespaces.put("content", CONTENT_NAMESPACE);
+        }
+        if self.self_link.is_some() {
+            namespaces.put("atom", ATOM_NAMESPACE);
+        }
+        for merged in &self.entries {
+            for extension in &merged.entry.extensions {
+                collect_namespaces(extension, &mut namespaces);
+            }
+        }
+        if !namespaces.is_empty() {
+            root_node.namespaces = Some(namespaces);
+        }
+
+        // `<title>`, `<link>` and `<description>` are required by RSS 2.0
         let mut channel = Element::new("channel");
-        let mut title = Element::new("title");
-        title.children.push(XMLNode::Text("MIOR".to_owned()));
-        channel.children.push(XMLNode::Element(title));
-        for e in self.elements {
-            channel.children.push(e.0);
+        channel.children.push(text_node("title", &self.title));
+        if let Some(home_link) = self.home_link() {
+            channel.children.push(text_node("link", &home_link));
+        }
+        let description = self.description.as_deref().unwrap_or(DEFAULT_DESCRIPTION);
+        channel.children.push(text_node("description", description));
+        if let Some(self_link) = &self.self_link {
+            let mut node_link = Element::new("link");
+            node_link.prefix = Some("atom".to_owned());
+            node_link.namespace = Some(ATOM_NAMESPACE.to_owned());
+            node_link.attributes.insert("href".to_owned(), self_link.clone());
+            node_link.attributes.insert("rel".to_owned(), "self".to_owned());
+            node_link
+                .attributes
+                .insert("type".to_owned(), "application/rss+xml".to_owned());
+            channel.children.push(XMLNode::Element(node_link));
+        }
+        channel
+            .children
+            .push(text_node("lastBuildDate", &self.updated().to_rfc2822()));
+        channel.children.push(text_node("generator", GENERATOR));
+        if let Some(ttl) = self.ttl {
+            channel.children.push(text_node("ttl", &ttl.to_string()));
+        }
+        for merged in self.entries {
+            channel.children.push(XMLNode::Element(rss_item(merged)));
         }
         root_node.children.push(XMLNode::Element(channel));
+        write_xml(&root_node)
+    }
+
+    fn build_atom(self) -> Vec<u8> {
+        let mut root_node = Element::new("feed");
+        let mut namespaces = Namespace::empty();
+        namespaces.put("", ATOM_NAMESPACE);
+        root_node.namespaces = Some(namespaces);
+
+        let updated = self.updated();
+        root_node
+            .children
+            .push(text_node("id", self.id.as_deref().unwrap_or(DEFAULT_ID)));
+        root_node.children.push(text_node("title", &self.title));
+        if let Some(description) = &self.description {
+            root_node.children.push(text_node("subtitle", description));
+        }
+        root_node.children.push(text_node("updated", &updated.to_rfc3339()));
+        // Atom requires `<author>` of the feed unless every entry has one, which
+        // items from merged sources often don't
+        let mut node_author = Element::new("author");
+        node_author.children.push(text_node("name", GENERATOR));
+        root_node.children.push(XMLNode::Element(node_author));
+        if let Some(home_link) = self.home_link() {
+            root_node.children.push(atom_link("alternate", &home_link));
+        }
+        if let Some(self_link) = &self.self_link {
+            root_node.children.push(atom_link("self", self_link));
+        }
+        root_node.children.push(text_node("generator", GENERATOR));
+        for merged in self.entries {
+            // Atom requires `<updated>` for every entry
+            let updated = merged.entry.pub_date.unwrap_or(updated);
+            root_node.children.push(XMLNode::Element(atom_entry(merged, updated)));
+        }
+        write_xml(&root_node)
+    }
+
+    fn build_json(self) -> Vec<u8> {
+        let home_page_url = self.home_link();
+        let feed = JsonFeed {
+            version: json_feed::VERSION.to_owned(),
+            title: self.title,
+            home_page_url,
+            feed_url: self.self_link,
+            description: self.description,
+            items: self.entries.into_iter().map(json_item).collect(),
+        };
+        serde_json::to_vec(&feed).unwrap()
+    }
+}
+
+/// Returns the guid of `entry` if it is globally unique (URLs, URNs, tag URIs,
+/// etc.), unlike plain serial numbers or titles which may collide between sites
+fn global_guid(entry: &FeedEntry) -> Option<&String> {
+    // Titles used as guids may contain colons too, e.g. `[Sub] Show (检索:Show)`
+    entry
+        .guid
+        .as_ref()
+        .filter(|g| !g.contains(char::is_whitespace) && Url::parse(g).is_ok())
+}
+
+/// Returns an absolute IRI identifying `entry` from `source`, as Atom requires
+/// for `<id>`. Local guids are qualified by the source feed, e.g.
+/// `urn:mior:entry:https%3A%2F%2Fexample%2Ecom%2Ffeed:123`.
+fn atom_entry_id(entry: &FeedEntry, source: &EntrySource) -> String {
+    if let Some(guid) = global_guid(entry) {
+        return guid.clone();
+    }
+    if matches!(Url::parse(&entry.link), Ok(url) if url.has_host()) {
+        return entry.link.clone();
+    }
+    let local_id = entry.guid.as_ref().unwrap_or(&entry.title);
+    format!(
+        "urn:mior:entry:{}:{}",
+        utf8_percent_encode(&source.url, NON_ALPHANUMERIC),
+        utf8_percent_encode(local_id, NON_ALPHANUMERIC)
+    )
+}
+
+/// Normalizes `link` so that trivially different URLs of the same page are
+/// considered equal, e.g. `http://www.example.com/a/?utm_source=x` and
+/// `https://example.com/a`. Fragments are kept, as they may route pages of
+/// single-page sites, e.g. `https://example.com/#/post/1`.
+fn normalize_link(link: &str) -> String {
+    let mut url = match Url::parse(link) {
+        Ok(url) if url.has_host() => url,
+        _ => return link.to_owned(),
+    };
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| !k.starts_with("utm_"))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+    let host = url.host_str().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let path = url.path().trim_end_matches('/');
+    let query = url.query().map(|query| format!("?{query}")).unwrap_or_default();
+    let fragment = url
+        .fragment()
+        .map(|fragment| format!("#{fragment}"))
+        .unwrap_or_default();
+    format!("{host}{path}{query}{fragment}")
+}
+
+/// Normalizes `title` for near-duplicate detection, ignoring case, width,
+/// punctuation and spaces
+fn normalize_title(title: &str) -> String {
+    title
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+fn json_item(merged: MergedEntry) -> JsonFeedItem {
+    let mut item = JsonFeedItem::from(merged.entry);
+    item.extension = Some(JsonFeedItemExtension {
+        sources: merged.sources.into_iter().map(|s| s.name).collect(),
+    });
+    item
+}
+
+/// Converts `merged` into an RSS item, with a `<source>` element referring to
+/// the feed it was first seen in
+fn rss_item(merged: MergedEntry) -> Element {
+    let MergedEntry { entry, sources } = merged;
+    let mut node_item = Element::new("item");
+    if !entry.title.is_empty() {
+        node_item.children.push(text_node("title", &entry.title));
+    }
+    node_item.children.push(text_node("link", &entry.link));
+    if let Some(guid) = entry.guid {
+        let mut node_guid = Element::new("guid");
+        if guid != entry.link {
+            node_guid
+                .attributes
+                .insert("isPermaLink".to_owned(), "false".to_owned());
+        }
+        node_guid.children.push(XMLNode::Text(guid));
+        node_item.children.push(XMLNode::Element(node_guid));
+    }
+    if let Some(pub_date) = entry.pub_date {
+        node_item.children.push(text_node("pubDate", &pub_date.to_rfc2822()));
+    }
+    if let Some(description) = entry.description {
+        node_item.children.push(text_node("description", &description));
+    }
+    if let Some(content) = entry.content {
+        let mut node_content = Element::new("encoded");
+        node_content.prefix = Some("content".to_owned());
+        node_content.namespace = Some(CONTENT_NAMESPACE.to_owned());
+        node_content.children.push(XMLNode::Text(content));
+        node_item.children.push(XMLNode::Element(node_content));
+    }
+    if let Some(author) = entry.author {
+        node_item.children.push(text_node("author", &author));
+    }
+    for category in entry.categories {
+        node_item.children.push(text_node("category", &category));
+    }
+    if let Some(comments) = entry.comments {
+        node_item.children.push(text_node("comments", &comments));
+    }
+    for enclosure in entry.enclosures {
+        let mut node_enclosure = Element::new("enclosure");
+        node_enclosure.attributes.insert("url".to_owned(), enclosure.url);
+        // Both `length` and `type` are required by RSS 2.0
+        let length = enclosure.length.unwrap_or_default().to_string();
+        node_enclosure.attributes.insert("length".to_owned(), length);
+        let mime_type = enclosure
+            .mime_type
+            .unwrap_or_else(|| "application/octet-stream".to_owned());
+        node_enclosure.attributes.insert("type".to_owned(), mime_type);
+        node_item.children.push(XMLNode::Element(node_enclosure));
+    }
+    let source = &sources[0];
+    let mut node_source = Element::new("source");
+    node_source.attributes.insert("url".to_owned(), source.url.clone());
+    node_source.children.push(XMLNode::Text(source.name.clone()));
+    node_item.children.push(XMLNode::Element(node_source));
+    // An item may have only one `<source>`, which is replaced by ours
+    for extension in entry.extensions {
+        if extension.prefix.is_some() || extension.name != "source" {
+            node_item.children.push(XMLNode::Element(extension));
+        }
+    }
+    node_item
+}
+
+fn atom_entry(merged: MergedEntry, updated: DateTime<Utc>) -> Element {
+    let MergedEntry { entry, sources } = merged;
+    let mut node_entry = Element::new("entry");
+    node_entry
+        .children
+        .push(text_node("id", &atom_entry_id(&entry, &sources[0])));
+    node_entry.children.push(text_node("title", &entry.title));
+    let mut node_link = Element::new("link");
+    node_link.attributes.insert("rel".to_owned(), "alternate".to_owned());
+    node_link.attributes.insert("href".to_owned(), entry.link);
+    node_entry.children.push(XMLNode::Element(node_link));
+    if let Some(pub_date) = entry.pub_date {
+        node_entry.children.push(text_node("published", &pub_date.to_rfc3339()));
+    }
+    node_entry.children.push(text_node("updated", &updated.to_rfc3339()));
+    if let Some(author) = entry.author {
+        let mut node_author = Element::new("author");
+        node_author.children.push(text_node("name", &author));
+        node_entry.children.push(XMLNode::Element(node_author));
+    }
+    if let Some(description) = entry.description {
+        node_entry.children.push(html_node("summary", &description));
+    }
+    if let Some(content) = entry.content {
+        node_entry.children.push(html_node("content", &content));
+    }
+    for category in entry.categories {
+        let mut node_category = Element::new("category");
+        node_category.attributes.insert("term".to_owned(), category);
+        node_entry.children.push(XMLNode::Element(node_category));
+    }
+    for enclosure in entry.enclosures {
+        let mut node_link = Element::new("link");
+        node_link.attributes.insert("rel".to_owned(), "enclosure".to_owned());
+        node_link.attributes.insert("href".to_owned(), enclosure.url);
+        if let Some(mime_type) = enclosure.mime_type {
+            node_link.attributes.insert("type".to_owned(), mime_type);
+        }
+        if let Some(length) = enclosure.length {
+            node_link.attributes.insert("length".to_owned(), length.to_string());
+        }
+        node_entry.children.push(XMLNode::Element(node_link));
+    }
+    if let Some(comments) = &entry.comments {
+        node_entry.children.push(atom_link("replies", comments));
+    }
+    let source = &sources[0];
+    let mut node_source = Element::new("source");
+    node_source.children.push(text_node("title", &source.name));
+    let mut node_link = Element::new("link");
+    node_link.attributes.insert("rel".to_owned(), "self".to_owned());
+    node_link.attributes.insert("href".to_owned(), source.url.clone());
+    node_source.children.push(XMLNode::Element(node_link));
+    node_entry.children.push(XMLNode::Element(node_source));
+    node_entry
+}
 
-        // Write out as text
-        let mut buf = Vec::new();
-        root_node.write(&mut buf).unwrap();
-        buf
+/// Collects namespaces of the prefixed elements in `e` and its descendants,
+/// so that they are declared once on the root instead of every item. A prefix
+/// already bound to another URI stays declared on the elements using it.
+fn collect_namespaces(e: &Element, namespaces: &mut Namespace) {
+    if let (Some(prefix), Some(uri)) = (&e.prefix, &e.namespace) {
+        namespaces.put(prefix.as_str(), uri.as_str());
+    }
+    for node in &e.children {
+        if let XMLNode::Element(child) = node {
+            collect_namespaces(child, namespaces);
+        }
     }
 }
 
+/// Link of an Atom feed, e.g. `<link rel="self" href="..."/>`
+fn atom_link(rel: &str, href: &str) -> XMLNode {
+    let mut e = Element::new("link");
+    e.attributes.insert("rel".to_owned(), rel.to_owned());
+    e.attributes.insert("href".to_owned(), href.to_owned());
+    XMLNode::Element(e)
+}
+
+/// Text construct of Atom which may contain escaped HTML
+fn html_node(name: &str, html: &str) -> XMLNode {
+    let mut e = Element::new(name);
+    e.attributes.insert("type".to_owned(), "html".to_owned());
+    e.children.push(XMLNode::Text(html.to_owned()));
+    XMLNode::Element(e)
+}
+
+fn write_xml(root_node: &Element) -> Vec<u8> {
+    let mut buf = Vec::new();
+    root_node.write(&mut buf).unwrap();
+    buf
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
 
+    use chrono_tz::Tz;
+
     use super::*;
+    use crate::util::feed_parser::FeedDocument;
 
     const PATH: &str = "./src/tests/data/";
 
+    macro_rules! test_case {
+        ($format:expr, $name:literal, $test_func:ident) => {
+            test_case!($format, $name, false, $test_func);
+        };
+        ($format:expr, $name:literal, $stored:literal, $test_func:ident) => {
+            #[test]
+            fn $test_func() {
+                test_merge_feeds($format, $name, $stored)
+            }
+        };
+    }
+
+    test_case!(OutputFormat::Rss, "merged.xml", test_merge_feeds_rss);
+    test_case!(OutputFormat::Atom, "merged.atom.xml", test_merge_feeds_atom);
+    test_case!(OutputFormat::Json, "merged.json", test_merge_feeds_json);
+    test_case!(OutputFormat::Rss, "merged.xml", true, test_merge_stored_feeds_rss);
+    test_case!(OutputFormat::Json, "merged.json", true, test_merge_stored_feeds_json);
+
+    const SELF_LINK: &str = "https://mior.example.com/rss?token=test";
+
+    const FEED_ID: &str = "tag:mior.example.com,2022:user/1";
+
+    fn source(name: &str) -> EntrySource {
+        EntrySource {
+            name: name.to_owned(),
+            url: format!("https://example.com/{name}"),
+        }
+    }
+
+    fn read_entries(name: &str) -> Vec<FeedEntry> {
+        let data = fs::read_to_string(format!("{PATH}/{name}")).unwrap();
+        FeedDocument::parse(data.as_bytes()).unwrap().into_entries().unwrap()
+    }
+
+    /// Builds a JSON Feed and returns the links of items along with their
+    /// sources
+    fn merged_links(merger: FeedMerger) -> Vec<(String, Vec<String>)> {
+        let feed: serde_json::Value = serde_json::from_slice(&merger.build(OutputFormat::Json)).unwrap();
+        feed["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| {
+                let sources = item["_mior"]["sources"].as_array().unwrap();
+                let sources = sources.iter().map(|s| s.as_str().unwrap().to_owned()).collect();
+                (item["url"].as_str().unwrap().to_owned(), sources)
+            })
+            .collect()
+    }
+
     #[test]
-    fn test_merge_feeds() {
+    fn test_dedup() {
+        let entries = read_entries("6.xml");
+        let mut copies = entries.clone();
+        for entry in &mut copies {
+            entry.guid = None;
+            entry.link = entry.link.replace("https://", "http://www.") + "/?utm_source=rss";
+        }
+        // The earliest copy is kept
+        copies[1].pub_date = copies[1].pub_date.map(|t| t - chrono::Duration::hours(1));
+
+        let mut merger = FeedMerger::new();
+        merger.append(&source("a"), entries.clone());
+        merger.append(&source("b"), copies.clone());
+        let links = merged_links(merger);
+        assert_eq!(links.len(), entries.len());
+        assert_eq!(links[0].1, ["a", "b"]);
+        assert_eq!(links[1].1, ["b", "a"]);
+        assert_eq!(links[1].0, copies[1].link);
+        assert_eq!(links[0].0, entries[0].link);
+
+        let mut retitled = entries[..1].to_vec();
+        retitled[0].guid = None;
+        retitled[0].link = "https://blog.rust-lang.org/2022/05/19/Rust-1.61.0.html".to_owned();
+        retitled[0].title = "ＲＵＳＴ 1.61.0!".to_owned();
+        for dedup_titles in [false, true] {
+            let mut merger = FeedMerger::new().with_dedup_titles(dedup_titles);
+            merger.append(&source("a"), entries.clone());
+            merger.append(&source("c"), retitled.clone());
+            let expected = if dedup_titles { entries.len() } else { entries.len() + 1 };
+            assert_eq!(merged_links(merger).len(), expected);
+        }
+    }
+
+    #[test]
+    fn test_dedup_by_guid_first() {
+        let entries = read_entries("6.xml");
+        // Items of one source with their own guids but a shared link
+        let mut shared_link = entries[..2].to_vec();
+        for entry in &mut shared_link {
+            entry.link = "https://github.com/rust-lang/rust/releases".to_owned();
+        }
+        let mut merger = FeedMerger::new();
+        merger.append(&source("a"), shared_link);
+        assert_eq!(merged_links(merger).len(), 2);
+
+        // Fragments routing single-page sites tell pages apart
+        let mut routed = entries[..2].to_vec();
+        for (i, entry) in routed.iter_mut().enumerate() {
+            entry.guid = None;
+            entry.link = format!("https://example.com/#/post/{i}");
+        }
         let mut merger = FeedMerger::new();
-        for name in ["1", "2", "3", "4", "5"] {
-            let data = fs::read_to_string(format!("{PATH}/{name}.xml")).unwrap();
-            let doc = FeedDocument::parse(data.as_bytes()).unwrap().with_limit(20);
-            merger.append(doc).unwrap();
+        merger.append(&source("a"), routed);
+        assert_eq!(merged_links(merger).len(), 2);
+    }
+
+    #[test]
+    fn test_sources() {
+        let entries = read_entries("6.xml");
+        for prefix_titles in [false, true] {
+            let mut merger = FeedMerger::new().with_prefix_titles(prefix_titles);
+            merger.append(&source("Rust Blog"), entries.clone());
+            let rss = Element::parse(merger.build(OutputFormat::Rss).as_slice()).unwrap();
+            let item = rss.get_child("channel").unwrap().get_child("item").unwrap();
+
+            let title = item.get_child("title").unwrap().get_text().unwrap();
+            if prefix_titles {
+                assert_eq!(title, format!("[Rust Blog] {}", entries[0].title));
+            } else {
+                assert_eq!(title, entries[0].title);
+            }
+            let node_source = item.get_child("source").unwrap();
+            assert_eq!(node_source.get_text().unwrap(), "Rust Blog");
+            assert_eq!(node_source.attributes["url"], "https://example.com/Rust Blog");
         }
+    }
 
-        let result = merger.build();
+    #[test]
+    fn test_channel_metadata() {
+        let merger = FeedMerger::new()
+            .with_title("Anime".to_owned())
+            .with_description("New releases".to_owned());
+        let rss = Element::parse(merger.build(OutputFormat::Rss).as_slice()).unwrap();
+        let channel = rss.get_child("channel").unwrap();
+        assert_eq!(channel.get_child("title").unwrap().get_text().unwrap(), "Anime");
+        assert_eq!(
+            channel.get_child("description").unwrap().get_text().unwrap(),
+            "New releases"
+        );
+
+        let merger = FeedMerger::new().with_description(String::new());
+        let feed: serde_json::Value = serde_json::from_slice(&merger.build(OutputFormat::Json)).unwrap();
+        assert_eq!(feed["title"], DEFAULT_TITLE);
+        assert!(feed.get("description").is_none());
+    }
+
+    /// Checks the elements required by RSS 2.0, see
+    /// <https://www.rssboard.org/rss-specification>
+    #[test]
+    fn test_rss_required_elements() {
+        let mut merger = FeedMerger::new().with_self_link(SELF_LINK.to_owned()).with_ttl(5);
+        merger.append(&source("a"), read_entries("6.xml"));
+        merger.append(&source("b"), read_entries("10.xml"));
+        let rss = Element::parse(merger.build(OutputFormat::Rss).as_slice()).unwrap();
+        assert_eq!(rss.attributes["version"], "2.0");
+
+        let channel = rss.get_child("channel").unwrap();
+        let text = |e: &Element, name: &str| e.get_child(name).and_then(|c| c.get_text()).map(|t| t.into_owned());
+        assert_eq!(text(channel, "title").unwrap(), DEFAULT_TITLE);
+        assert_eq!(text(channel, "link").unwrap(), "https://mior.example.com/");
+        assert_eq!(text(channel, "description").unwrap(), DEFAULT_DESCRIPTION);
+        assert_eq!(text(channel, "generator").unwrap(), GENERATOR);
+        assert_eq!(text(channel, "ttl").unwrap(), "5");
+        assert!(DateTime::parse_from_rfc2822(&text(channel, "lastBuildDate").unwrap()).is_ok());
+
+        let self_link = channel.get_child(("link", ATOM_NAMESPACE)).unwrap();
+        assert_eq!(self_link.attributes["rel"], "self");
+        assert_eq!(self_link.attributes["href"], SELF_LINK);
+
+        // All elements of an item are optional, but at least one of title or
+        // description must be present
+        let items: Vec<_> = channel
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .filter(|e| e.name == "item")
+            .collect();
+        assert!(!items.is_empty());
+        for item in items {
+            assert!(text(item, "title").is_some() || text(item, "description").is_some());
+        }
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let mut entries = read_entries("6.xml");
+        entries.truncate(2);
+        let extension = |xml: &str| Element::parse(xml.as_bytes()).unwrap();
+        entries[0].extensions = vec![extension(
+            r#"<itunes:duration xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">42:00</itunes:duration>"#,
+        )];
+        // The prefix is bound to another URI than the first one
+        entries[1].extensions = vec![extension(
+            r#"<itunes:duration xmlns:itunes="https://example.com/itunes">42:00</itunes:duration>"#,
+        )];
+        let mut merger = FeedMerger::new();
+        merger.append(&source("a"), entries);
+        let result = String::from_utf8(merger.build(OutputFormat::Rss)).unwrap();
+        let root_tag = &result[..result.find("<channel>").unwrap()];
+        assert!(root_tag.contains(r#"xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd""#));
+        assert!(result.contains("<itunes:duration>42:00</itunes:duration>"));
+        assert!(result.contains(r#"<itunes:duration xmlns:itunes="https://example.com/itunes">"#));
+
+        let rss = Element::parse(result.as_bytes()).unwrap();
+        let namespaces: Vec<_> = rss
+            .get_child("channel")
+            .unwrap()
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .filter_map(|e| e.get_child("duration"))
+            .map(|e| e.namespace.clone().unwrap())
+            .collect();
+        assert_eq!(
+            namespaces,
+            [
+                "http://www.itunes.com/dtds/podcast-1.0.dtd",
+                "https://example.com/itunes"
+            ]
+        );
+    }
+
+    /// Checks that `<id>` of every Atom entry is a unique absolute IRI, even
+    /// for guids which are titles (e.g. mikanani.me) or serial numbers
+    #[test]
+    fn test_atom_entry_ids() {
+        let mut merger = FeedMerger::new();
+        merger.append(&source("1.xml"), read_entries("1.xml"));
+        for name in ["a", "b"] {
+            // Same serial number with a relative link in each source
+            let mut serials = read_entries("6.xml");
+            serials.truncate(1);
+            serials[0].guid = Some("123".to_owned());
+            serials[0].link = format!("/{name}/123");
+            merger.append(&source(name), serials);
+        }
+        let atom = Element::parse(merger.build(OutputFormat::Atom).as_slice()).unwrap();
+
+        let ids: Vec<_> = atom
+            .children
+            .iter()
+            .filter_map(|n| n.as_element())
+            .filter(|e| e.name == "entry")
+            .map(|e| e.get_child("id").unwrap().get_text().unwrap().into_owned())
+            .collect();
+        assert!(ids.len() > 2);
+        for id in &ids {
+            assert!(Url::parse(id).is_ok(), "{id}");
+            assert!(!id.contains(char::is_whitespace), "{id}");
+        }
+        let mut unique_ids = ids.clone();
+        unique_ids.sort();
+        unique_ids.dedup();
+        assert_eq!(unique_ids.len(), ids.len());
+    }
+
+    /// Merges the test feeds into `name`. If `stored`, the entries are passed
+    /// through the serialization of the item store first.
+    fn test_merge_feeds(format: OutputFormat, name: &str, stored: bool) {
+        let mut merger = FeedMerger::new()
+            .with_id(FEED_ID.to_owned())
+            .with_self_link(SELF_LINK.to_owned())
+            .with_ttl(5);
+        for name in [
+            "1.xml", "2.xml", "3.xml", "4.xml", "5.xml", "6.xml", "7.xml", "8.xml", "9.json",
+        ] {
+            let data = fs::read_to_string(format!("{PATH}/{name}")).unwrap();
+            // Dates of mikanani.me are in China Standard Time without offsets
+            let timezone = if name == "1.xml" { Tz::Asia__Shanghai } else { Tz::UTC };
+            let doc = FeedDocument::parse(data.as_bytes())
+                .unwrap()
+                .with_timezone(timezone)
+                .with_limit(20);
+            let mut entries = doc.into_entries().unwrap();
+            if stored {
+                entries = entries
+                    .iter()
+                    .map(|e| serde_json::from_str(&serde_json::to_string(e).unwrap()).unwrap())
+                    .collect();
+            }
+            merger.append(&source(name), entries);
+        }
+
+        let result = merger.build(format);
 
         // Uncomment following lines to generate result files
         // {
         //     use std::fs::File;
         //     use std::io::{BufWriter, Write};
-        //     let file = File::create(format!("{PATH}/merged.xml")).unwrap();
+        //     let file = File::create(format!("{PATH}/{name}")).unwrap();
         //     let mut writer = BufWriter::new(file);
         //     writer.write_all(result.as_slice()).unwrap();
         //     writer.flush().unwrap();
         // }
 
-        let expected = fs::read_to_string(format!("{PATH}/merged.xml")).unwrap();
-
-        // NOTE: only checked length because the text representation changes every time,
-        // perhaps caused by the HashMap of attributes
-        assert_eq!(expected.len(), result.len());
+        let expected = fs::read_to_string(format!("{PATH}/{name}")).unwrap();
+        assert_eq!(expected, String::from_utf8(result).unwrap());
     }
 }
@@ -1,30 +1,123 @@
-use chrono::{DateTime, Utc};
-use xmltree::{Element, XMLNode};
+use chrono_tz::Tz;
+use xmltree::{Element, ElementPredicate, EmitterConfig, XMLNode};
 
 use crate::error::MalformedFeedError;
 use crate::model::{FeedInfo, FeedItem, FeedMeta};
+use crate::util::date_parser::parse_date;
+use crate::util::encoding::{charset_param, decode_document};
+use crate::util::feed_entry::{Enclosure, FeedEntry};
+use crate::util::feed_filter::FeedFilter;
+use crate::util::json_feed::JsonFeed;
 
 pub type Result<T> = std::result::Result<T, MalformedFeedError>;
 
+pub(super) const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+const RDF_NAMESPACE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+pub(super) const CONTENT_NAMESPACE: &str = "http://purl.org/rss/1.0/modules/content/";
+
+/// Syndication formats understood by [`FeedDocument`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    /// RSS 2.0, i.e. `rss > channel > item`
+    Rss,
+    /// Atom 1.0, i.e. `feed > entry`
+    Atom,
+    /// RSS 1.0, i.e. `rdf:RDF > item` where items are siblings of `channel`
+    Rdf,
+    /// JSON Feed 1.0 / 1.1, which is converted into RSS 2.0 when parsing
+    Json,
+}
+
+impl FeedFormat {
+    fn detect(root_node: &Element) -> Result<Self> {
+        match root_node.name.as_str() {
+            "rss" => Ok(FeedFormat::Rss),
+            "feed" if root_node.namespace.as_deref() == Some(ATOM_NAMESPACE) => Ok(FeedFormat::Atom),
+            "RDF" if root_node.namespace.as_deref() == Some(RDF_NAMESPACE) => Ok(FeedFormat::Rdf),
+            name => Err(MalformedFeedError::UnknownFormat(name.to_owned())),
+        }
+    }
+
+    /// Guesses the format from the `Content-Type` header of a response
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        match mime {
+            "application/feed+json" | "application/json" => Some(FeedFormat::Json),
+            "application/rss+xml" => Some(FeedFormat::Rss),
+            "application/atom+xml" => Some(FeedFormat::Atom),
+            "application/rdf+xml" => Some(FeedFormat::Rdf),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the first non-whitespace character of the
+    /// document
+    fn sniff(data: &[u8]) -> Self {
+        let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        match data.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => FeedFormat::Json,
+            _ => FeedFormat::Rss,
+        }
+    }
+
+    /// Name of the elements holding feed items
+    fn item_tag(self) -> &'static str {
+        match self {
+            FeedFormat::Rss | FeedFormat::Rdf | FeedFormat::Json => "item",
+            FeedFormat::Atom => "entry",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FeedDocument {
     root_node: Element,
 
+    format: FeedFormat,
+
     limit: usize,
 
-    keywords: Vec<String>,
+    filter: FeedFilter,
+
+    /// Timezone of dates without offsets
+    timezone: Tz,
 }
 
 impl FeedDocument {
+    /// Parses `data` in whatever format and encoding it's in
+    #[cfg(test)]
     pub fn parse(data: &[u8]) -> Result<Self> {
-        let root_node = Element::parse(data)?;
-        if root_node.name != "rss" {
-            return Err(MalformedFeedError::TagNotFound("rss"));
+        Self::parse_with_content_type(data, None)
+    }
+
+    /// Parses `data` according to the `Content-Type` of the response, or sniffs
+    /// the format if it is missing or too generic (e.g. `text/xml`). `data` is
+    /// transcoded into UTF-8 first if it's in another encoding.
+    pub fn parse_with_content_type(data: &[u8], content_type: Option<&str>) -> Result<Self> {
+        let data = decode_document(data, content_type.and_then(charset_param));
+        match content_type.and_then(FeedFormat::from_content_type) {
+            Some(FeedFormat::Json) => Self::parse_as(&data, FeedFormat::Json),
+            // XML formats are told apart by their root element anyway
+            _ => Self::parse_as(&data, FeedFormat::sniff(&data)),
         }
+    }
+
+    fn parse_as(data: &[u8], format: FeedFormat) -> Result<Self> {
+        let (root_node, format) = match format {
+            FeedFormat::Json => (JsonFeed::parse(data)?.into_rss(), FeedFormat::Json),
+            _ => {
+                let root_node = Element::parse(data)?;
+                let format = FeedFormat::detect(&root_node)?;
+                (root_node, format)
+            }
+        };
         Ok(Self {
             root_node,
+            format,
             limit: usize::MAX,
-            keywords: Vec::new(),
+            filter: FeedFilter::default(),
+            timezone: Tz::UTC,
         })
     }
 
@@ -33,33 +126,62 @@ impl FeedDocument {
         self
     }
 
-    pub fn with_keywords(mut self, keywords: Vec<String>) -> Self {
-        self.keywords = keywords;
+    pub fn with_filter(mut self, filter: FeedFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
         self
     }
 
     pub fn read_feed(&self) -> Result<FeedInfo> {
-        let node_channel = self
-            .root_node
-            .get_child("channel")
-            .ok_or(MalformedFeedError::TagNotFound("channel"))?;
-
-        let meta = Self::read_meta(node_channel)?;
-        let items = node_channel
-            .children
-            .iter()
-            .flat_map(|node| match node {
-                XMLNode::Element(e) if e.name == "item" => Some(e),
-                _ => None,
-            })
-            .filter(|e| self.filter_by_keywords(e))
-            .take(self.limit)
-            .map(Self::read_item)
-            .try_collect()?;
+        let meta = Self::read_meta(self.channel_node()?)?;
+        let items = self.entries()?.map(FeedItem::from).collect();
 
         Ok(FeedInfo { meta, items })
     }
 
+    pub fn into_entries(self) -> Result<Vec<FeedEntry>> {
+        Ok(self.entries()?.collect())
+    }
+
+    /// Reads the items which pass the filters. Items that can't be read (e.g.
+    /// missing `<link>`) are skipped.
+    fn entries(&self) -> Result<impl Iterator<Item = FeedEntry> + '_> {
+        let entries = self
+            .item_nodes()?
+            .flat_map(|e| self.read_entry(e).ok())
+            .filter(|entry| self.filter.matches(entry))
+            .take(self.limit);
+        Ok(entries)
+    }
+
+    /// Returns the element holding feed metadata
+    fn channel_node(&self) -> Result<&Element> {
+        match self.format {
+            FeedFormat::Rss | FeedFormat::Rdf | FeedFormat::Json => self
+                .root_node
+                .get_child("channel")
+                .ok_or(MalformedFeedError::TagNotFound("channel")),
+            FeedFormat::Atom => Ok(&self.root_node),
+        }
+    }
+
+    /// Returns the element whose children are the feed items
+    fn items_parent_node(&self) -> Result<&Element> {
+        match self.format {
+            FeedFormat::Rss | FeedFormat::Json => self.channel_node(),
+            FeedFormat::Atom | FeedFormat::Rdf => Ok(&self.root_node),
+        }
+    }
+
+    fn item_nodes(&self) -> Result<impl Iterator<Item = &Element>> {
+        let item_tag = self.format.item_tag();
+        Ok(child_elements(self.items_parent_node()?).filter(move |e| e.name == item_tag))
+    }
+
     fn read_meta(node_channel: &Element) -> Result<FeedMeta> {
         let title = node_channel
             .get_child("title")
@@ -70,83 +192,259 @@ impl FeedDocument {
         Ok(FeedMeta { title })
     }
 
-    fn read_item(node_item: &Element) -> Result<FeedItem> {
+    fn read_entry(&self, node_item: &Element) -> Result<FeedEntry> {
+        match self.format {
+            FeedFormat::Rss | FeedFormat::Json => self.read_rss_item(node_item, true),
+            FeedFormat::Rdf => self.read_rss_item(node_item, false),
+            FeedFormat::Atom => self.read_atom_entry(node_item),
+        }
+    }
+
+    /// Reads an RSS 2.0 or RSS 1.0 item. Unknown elements without namespace
+    /// prefix are only kept as extensions for RSS 2.0, because they belong
+    /// to the RSS 1.0 namespace in RSS 1.0.
+    fn read_rss_item(&self, node_item: &Element, keep_unprefixed: bool) -> Result<FeedEntry> {
+        // Title is optional in RSS 2.0, e.g. for posts of Mastodon
         let title = node_item
             .get_child("title")
-            .ok_or(MalformedFeedError::TagNotFound("title"))?
-            .get_text()
-            .ok_or(MalformedFeedError::InvalidTag("title"))?
-            .into_owned();
+            .and_then(|e| e.get_text())
+            .map(|t| t.into_owned())
+            .unwrap_or_default();
         let link = node_item
             .get_child("link")
             .ok_or(MalformedFeedError::TagNotFound("link"))?
             .get_text()
             .ok_or(MalformedFeedError::InvalidTag("link"))?
             .into_owned();
-        let mut pub_date: Option<DateTime<Utc>> = node_item
-            .get_child("pubDate")
-            .and_then(|e| e.get_text())
-            .and_then(|t| DateTime::parse_from_rfc2822(t.as_ref()).ok())
-            .map(|ts| ts.into());
-        if pub_date.is_none() {
-            // Adapt to mikanani.me. Example:
+        let pub_date = [
+            node_item.get_child("pubDate"),
+            // Adapt to mikanani.me, whose dates are local time of the feed. Example:
             // <torrent><pubDate>2022-05-23T22:18:57.333</pubDate></torrent>
-            pub_date = node_item
-                .get_child("torrent")
-                .and_then(|e| e.get_child("pubDate"))
+            node_item.get_child("torrent").and_then(|e| e.get_child("pubDate")),
+            // RSS 1.0 (and some RSS 2.0 feeds) use Dublin Core. Example:
+            // <dc:date>2022-05-24T12:05:00+09:00</dc:date>
+            node_item.get_child(("date", DC_NAMESPACE)),
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|e| e.get_text())
+        .find_map(|t| parse_date(&t, self.timezone));
+
+        let categories = child_elements(node_item)
+            .filter(|e| e.name == "category" || (e.name == "subject" && is_dc(e)))
+            .flat_map(|e| e.get_text())
+            .map(|t| t.into_owned())
+            .collect();
+        let enclosures = child_elements(node_item)
+            .filter(|e| e.name == "enclosure")
+            .flat_map(|e| {
+                Some(Enclosure {
+                    url: e.attributes.get("url")?.clone(),
+                    length: e.attributes.get("length").and_then(|l| l.parse().ok()),
+                    mime_type: e.attributes.get("type").cloned(),
+                })
+            })
+            .collect();
+        let extensions = child_elements(node_item)
+            .filter(|e| !is_known_rss_element(e) && (keep_unprefixed || e.prefix.is_some()))
+            .map(extension_element)
+            .collect();
+
+        Ok(FeedEntry {
+            title,
+            link,
+            guid: child_text(node_item, "guid"),
+            pub_date,
+            description: child_text(node_item, "description"),
+            content: child_text(node_item, ("encoded", CONTENT_NAMESPACE)),
+            author: child_text(node_item, "author").or_else(|| child_text(node_item, ("creator", DC_NAMESPACE))),
+            categories,
+            // Not to be confused with e.g. `<slash:comments>`, the number of comments
+            comments: child_elements(node_item)
+                .find(|e| e.name == "comments" && e.prefix.is_none())
                 .and_then(|e| e.get_text())
-                .and_then(|t| DateTime::parse_from_rfc3339(&(t.to_owned() + "+08:00")).ok())
-                .map(|ts| ts.into())
-        }
-        Ok(FeedItem { title, link, pub_date })
+                .map(|t| t.into_owned()),
+            enclosures,
+            extensions,
+        })
     }
 
-    pub fn into_item_nodes(mut self) -> Result<Vec<(XMLNode, Option<DateTime<Utc>>)>> {
-        let node_channel = self
-            .root_node
-            .take_child("channel")
-            .ok_or(MalformedFeedError::TagNotFound("channel"))?;
+    fn read_atom_entry(&self, node_entry: &Element) -> Result<FeedEntry> {
+        let node_title = node_entry
+            .get_child("title")
+            .ok_or(MalformedFeedError::TagNotFound("title"))?;
+        let title = atom_text(node_title, false).ok_or(MalformedFeedError::InvalidTag("title"))?;
+        let link = Self::atom_link_of(node_entry, "alternate")
+            .ok_or(MalformedFeedError::TagNotFound("link"))?
+            .to_owned();
+        // Prefer the original publication time and fall back to the last update
+        let pub_date = ["published", "updated"]
+            .iter()
+            .flat_map(|&name| node_entry.get_child(name))
+            .flat_map(|e| e.get_text())
+            .find_map(|t| parse_date(&t, self.timezone));
 
-        let items: Vec<_> = node_channel
-            .children
-            .into_iter()
-            .flat_map(|node| match node {
-                XMLNode::Element(ref e) if e.name == "item" => Some(node),
-                _ => None,
-            })
-            .filter(|node| {
-                if let XMLNode::Element(ref e) = node {
-                    self.filter_by_keywords(e)
-                } else {
-                    unreachable!()
-                }
-            })
-            .map(|node| {
-                let pub_date = if let XMLNode::Element(ref e) = node {
-                    FeedDocument::read_item(e).ok().and_then(|item| item.pub_date)
-                } else {
-                    unreachable!()
-                };
-                (node, pub_date)
+        let categories = child_elements(node_entry)
+            .filter(|e| e.name == "category")
+            .flat_map(|e| e.attributes.get("label").or_else(|| e.attributes.get("term")))
+            .cloned()
+            .collect();
+        let enclosures = child_elements(node_entry)
+            .filter(|e| e.name == "link" && e.attributes.get("rel").map(|rel| rel.as_str()) == Some("enclosure"))
+            .flat_map(|e| {
+                Some(Enclosure {
+                    url: e.attributes.get("href")?.clone(),
+                    length: e.attributes.get("length").and_then(|l| l.parse().ok()),
+                    mime_type: e.attributes.get("type").cloned(),
+                })
             })
-            .take(self.limit)
+            .collect();
+        // Elements of the Atom namespace are either modeled or meaningless in RSS
+        let extensions = child_elements(node_entry)
+            .filter(|e| e.prefix.is_some())
+            .map(extension_element)
             .collect();
 
-        Ok(items)
+        Ok(FeedEntry {
+            title,
+            link,
+            guid: child_text(node_entry, "id"),
+            pub_date,
+            description: node_entry.get_child("summary").and_then(|e| atom_text(e, true)),
+            content: node_entry.get_child("content").and_then(|e| atom_text(e, true)),
+            author: node_entry.get_child("author").and_then(|e| child_text(e, "name")),
+            categories,
+            comments: Self::atom_link_of(node_entry, "replies").map(|href| href.to_owned()),
+            enclosures,
+            extensions,
+        })
     }
 
-    fn filter_by_keywords(&self, e: &Element) -> bool {
-        if self.keywords.is_empty() {
-            return true;
+    /// Returns `href` of the first link of relation `rel`. A link without
+    /// `rel` is an alternate link.
+    fn atom_link_of<'a>(node_entry: &'a Element, rel: &str) -> Option<&'a str> {
+        child_elements(node_entry)
+            .filter(|e| e.name == "link")
+            .find(|e| e.attributes.get("rel").map_or("alternate", |rel| rel.as_str()) == rel)
+            .and_then(|e| e.attributes.get("href"))
+            .map(|href| href.as_str())
+    }
+}
+
+fn child_elements(e: &Element) -> impl Iterator<Item = &Element> {
+    e.children.iter().flat_map(|node| match node {
+        XMLNode::Element(e) => Some(e),
+        _ => None,
+    })
+}
+
+fn child_text<P: ElementPredicate>(e: &Element, k: P) -> Option<String> {
+    e.get_child(k).and_then(|e| e.get_text()).map(|t| t.into_owned())
+}
+
+/// Reads an Atom text construct. The markup of `type="xhtml"` is what's inside
+/// its wrapping `div`, which is reduced to plain text unless `markup` is set.
+fn atom_text(e: &Element, markup: bool) -> Option<String> {
+    if e.attributes.get("type").map(|t| t.as_str()) != Some("xhtml") {
+        return e.get_text().map(|t| t.into_owned());
+    }
+    let div = e.get_child("div")?;
+    let mut text = String::new();
+    for node in div.children.iter() {
+        if markup {
+            write_xhtml(node, &mut text)?;
+        } else {
+            push_text(node, &mut text);
         }
-        if let Some(c) = e.get_child("title") {
-            if let Some(t) = c.get_text() {
-                return self.keywords.iter().all(|w| t.contains(w));
-            }
+    }
+    Some(text.trim().to_owned())
+}
+
+/// Serializes a node of XHTML content as HTML, without namespace declarations
+fn write_xhtml(node: &XMLNode, out: &mut String) -> Option<()> {
+    match node {
+        XMLNode::Element(e) => {
+            let mut e = e.clone();
+            strip_namespaces(&mut e);
+            let mut buf = Vec::new();
+            let config = EmitterConfig::new().write_document_declaration(false);
+            e.write_with_config(&mut buf, config).ok()?;
+            out.push_str(std::str::from_utf8(&buf).ok()?);
         }
-        // Simply returns false for elements without title
-        false
+        XMLNode::Text(t) | XMLNode::CData(t) => {
+            out.push_str(&t.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"));
+        }
+        _ => {}
     }
+    Some(())
+}
+
+/// Appends the text of `node` and its descendants
+fn push_text(node: &XMLNode, out: &mut String) {
+    match node {
+        XMLNode::Element(e) => e.children.iter().for_each(|child| push_text(child, out)),
+        XMLNode::Text(t) | XMLNode::CData(t) => out.push_str(t),
+        _ => {}
+    }
+}
+
+fn is_dc(e: &Element) -> bool {
+    e.namespace.as_deref() == Some(DC_NAMESPACE)
+}
+
+/// Whether the element of an RSS item is modeled in [`FeedEntry`]
+fn is_known_rss_element(e: &Element) -> bool {
+    match e.namespace.as_deref() {
+        Some(DC_NAMESPACE) => matches!(e.name.as_str(), "creator" | "date" | "subject"),
+        Some(CONTENT_NAMESPACE) => e.name == "encoded",
+        _ if e.prefix.is_none() => matches!(
+            e.name.as_str(),
+            "title" | "link" | "guid" | "pubDate" | "description" | "author" | "category" | "comments" | "enclosure"
+        ),
+        _ => false,
+    }
+}
+
+/// Copies an element to be written into another document. Declaration of the
+/// default namespace is dropped since it comes from the source document, e.g.
+/// RSS 1.0 or Atom.
+fn extension_element(e: &Element) -> Element {
+    let mut e = e.clone();
+    strip_default_namespace(&mut e);
+    e
+}
+
+/// Removes the default namespace declaration from `e` and its descendants,
+/// leaving prefixed ones
+fn strip_default_namespace(e: &mut Element) {
+    if e.prefix.is_none() {
+        e.namespace = None;
+    }
+    if let Some(namespaces) = e.namespaces.as_mut() {
+        namespaces.0.remove("");
+    }
+    for node in e.children.iter_mut() {
+        if let XMLNode::Element(child) = node {
+            strip_default_namespace(child);
+        }
+    }
+}
+
+/// Removes all namespace declarations from `e` and its descendants
+fn strip_namespaces(e: &mut Element) {
+    e.namespace = None;
+    e.namespaces = None;
+    for node in e.children.iter_mut() {
+        if let XMLNode::Element(child) = node {
+            strip_namespaces(child);
+        }
+    }
+}
+
+pub(super) fn text_node(name: &str, text: &str) -> XMLNode {
+    let mut e = Element::new(name);
+    e.children.push(XMLNode::Text(text.to_owned()));
+    XMLNode::Element(e)
 }
 
 #[cfg(test)]
@@ -161,24 +459,63 @@ mod tests {
 
     macro_rules! test_case {
         ($name:literal, $test_func:ident) => {
+            test_case!($name, "xml", $test_func);
+        };
+        ($name:literal, $ext:literal, $test_func:ident) => {
+            test_case!($name, $ext, Tz::UTC, $test_func);
+        };
+        ($name:literal, $ext:literal, $timezone:expr, $test_func:ident) => {
             #[test]
             fn $test_func() {
-                test_xml($name)
+                test_feed($name, $ext, $timezone, None)
             }
         };
     }
 
-    test_case!("1", test_xml_1);
+    // Dates of mikanani.me are in China Standard Time without offsets
+    test_case!("1", "xml", Tz::Asia__Shanghai, test_xml_1);
     test_case!("2", test_xml_2);
     test_case!("3", test_xml_3);
     test_case!("4", test_xml_4);
     test_case!("5", test_xml_5);
+    test_case!("6", test_xml_6);
+    test_case!("7", test_xml_7);
+    test_case!("8", test_xml_8);
+    test_case!("10", test_xml_10);
+    test_case!("11", test_xml_11);
+    // Feeds in GBK, Shift_JIS and Big5
+    test_case!("12", test_xml_12);
+    test_case!("13", test_xml_13);
+    test_case!("14", test_xml_14);
+    // Atom text constructs of type xhtml
+    test_case!("17", test_xml_17);
+    test_case!("9", "json", test_json_9);
+    // Numeric item ids
+    test_case!("16", "json", test_json_16);
+
+    // UTF-8 feed sent with a wrong charset
+    #[test]
+    fn test_xml_15() {
+        test_feed("15", "xml", Tz::UTC, Some("text/xml; charset=ISO-8859-1"))
+    }
+
+    #[test]
+    fn test_skip_unreadable_items() {
+        let data = r#"<rss version="2.0"><channel><title>Feed</title>
+            <item><title>No link</title></item>
+            <item><title>Item</title><link>https://example.com/item</link></item>
+        </channel></rss>"#;
+        let feed_info = FeedDocument::parse(data.as_bytes()).unwrap().read_feed().unwrap();
+        assert_eq!(feed_info.items.len(), 1);
+        assert_eq!(feed_info.items[0].title, "Item");
+    }
 
-    fn test_xml(name: &str) {
+    fn test_feed(name: &str, ext: &str, timezone: Tz, content_type: Option<&str>) {
         let result = {
-            let data = fs::read_to_string(format!("{PATH}/{name}.xml")).unwrap();
-            let feed_info = FeedDocument::parse(data.as_bytes())
+            let data = fs::read(format!("{PATH}/{name}.{ext}")).unwrap();
+            let feed_info = FeedDocument::parse_with_content_type(&data, content_type)
                 .unwrap()
+                .with_timezone(timezone)
                 .with_limit(20)
                 .read_feed()
                 .unwrap();
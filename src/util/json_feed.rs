@@ -0,0 +1,255 @@
+use chrono::DateTime;
+use rocket::serde::json::serde_json;
+use rocket::serde::{Deserialize, Deserializer, Serialize};
+use xmltree::{Element, XMLNode};
+
+use crate::error::MalformedFeedError;
+use crate::util::feed_entry::FeedEntry;
+use crate::util::feed_parser::{text_node, CONTENT_NAMESPACE};
+
+type Result<T> = std::result::Result<T, MalformedFeedError>;
+
+const VERSION_PREFIX: &str = "https://jsonfeed.org/version/1";
+
+pub const VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// Max length (in chars) of titles derived from item content
+const DERIVED_TITLE_LENGTH: usize = 100;
+
+/// JSON Feed 1.0 / 1.1 document. See <https://www.jsonfeed.org/version/1.1/>
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JsonFeed {
+    pub version: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub home_page_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub items: Vec<JsonFeedItem>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JsonFeedItem {
+    #[serde(deserialize_with = "deserialize_id")]
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_modified: Option<String>,
+    /// Single author of JSON Feed 1.0, superseded by `authors` in 1.1
+    #[serde(skip_serializing)]
+    pub author: Option<JsonFeedAuthor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<JsonFeedAuthor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<JsonFeedAttachment>,
+    /// Custom extension of MIOR, written in the merged feed only
+    #[serde(rename = "_mior", skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<JsonFeedItemExtension>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JsonFeedAuthor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JsonFeedAttachment {
+    pub url: String,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_in_bytes: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JsonFeedItemExtension {
+    /// Names of the source feeds which carried the item
+    pub sources: Vec<String>,
+}
+
+impl JsonFeed {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let feed: JsonFeed = serde_json::from_slice(data)?;
+        if !feed.version.starts_with(VERSION_PREFIX) {
+            return Err(MalformedFeedError::InvalidTag("version"));
+        }
+        Ok(feed)
+    }
+
+    /// Converts into an equivalent RSS 2.0 document
+    pub fn into_rss(self) -> Element {
+        let mut channel = Element::new("channel");
+        channel.children.push(text_node("title", &self.title));
+        if let Some(home_page_url) = &self.home_page_url {
+            channel.children.push(text_node("link", home_page_url));
+        }
+        if let Some(description) = &self.description {
+            channel.children.push(text_node("description", description));
+        }
+        for item in self.items {
+            if let Some(node_item) = item.into_rss_item() {
+                channel.children.push(XMLNode::Element(node_item));
+            }
+        }
+
+        let mut root_node = Element::new("rss");
+        root_node.attributes.insert("version".to_owned(), "2.0".to_owned());
+        root_node.children.push(XMLNode::Element(channel));
+        root_node
+    }
+}
+
+impl JsonFeedItem {
+    /// Converts into an RSS `<item>`. Returns `None` for items without any
+    /// link.
+    fn into_rss_item(self) -> Option<Element> {
+        let link = self.url.as_ref().or(self.external_url.as_ref())?;
+        let mut node_item = Element::new("item");
+
+        // Title is optional in JSON Feed (e.g. micro-blog posts), so derive one from
+        // the content
+        let title = self
+            .title
+            .clone()
+            .filter(|t| !t.is_empty())
+            .or_else(|| self.summary.clone())
+            .or_else(|| {
+                self.content_text
+                    .as_deref()
+                    .and_then(|t| t.lines().next())
+                    .map(truncate_title)
+            })
+            .unwrap_or_else(|| link.clone());
+        node_item.children.push(text_node("title", &title));
+        node_item.children.push(text_node("link", link));
+
+        let mut guid = Element::new("guid");
+        guid.attributes.insert("isPermaLink".to_owned(), "false".to_owned());
+        guid.children.push(XMLNode::Text(self.id.clone()));
+        node_item.children.push(XMLNode::Element(guid));
+
+        let pub_date = [&self.date_published, &self.date_modified]
+            .into_iter()
+            .flatten()
+            .find_map(|t| DateTime::parse_from_rfc3339(t).ok());
+        if let Some(pub_date) = pub_date {
+            node_item.children.push(text_node("pubDate", &pub_date.to_rfc2822()));
+        }
+
+        // The other way round of `From<FeedEntry>`, full content goes to
+        // `<content:encoded>` while the summary stays as description
+        if let Some(summary) = self.summary {
+            node_item.children.push(text_node("description", &summary));
+        }
+        if let Some(content) = self.content_html.or(self.content_text) {
+            let mut node_content = Element::new("encoded");
+            node_content.prefix = Some("content".to_owned());
+            node_content.namespace = Some(CONTENT_NAMESPACE.to_owned());
+            node_content.children.push(XMLNode::Text(content));
+            node_item.children.push(XMLNode::Element(node_content));
+        }
+        let author = self.authors.into_iter().chain(self.author).find_map(|a| a.name);
+        if let Some(author) = author {
+            node_item.children.push(text_node("author", &author));
+        }
+        for tag in &self.tags {
+            node_item.children.push(text_node("category", tag));
+        }
+        for attachment in self.attachments {
+            let mut enclosure = Element::new("enclosure");
+            enclosure.attributes.insert("url".to_owned(), attachment.url);
+            enclosure.attributes.insert("type".to_owned(), attachment.mime_type);
+            if let Some(size) = attachment.size_in_bytes {
+                enclosure.attributes.insert("length".to_owned(), size.to_string());
+            }
+            node_item.children.push(XMLNode::Element(enclosure));
+        }
+        Some(node_item)
+    }
+}
+
+impl From<FeedEntry> for JsonFeedItem {
+    fn from(entry: FeedEntry) -> Self {
+        // Use the description as summary only if there is a separate full content
+        let (content_html, summary) = match entry.content {
+            Some(content) => (Some(content), entry.description),
+            None => (entry.description, None),
+        };
+        JsonFeedItem {
+            id: entry.guid.unwrap_or_else(|| entry.link.clone()),
+            url: Some(entry.link),
+            title: Some(entry.title).filter(|t| !t.is_empty()),
+            content_html,
+            summary,
+            date_published: entry.pub_date.map(|t| t.to_rfc3339()),
+            authors: entry
+                .author
+                .map(|name| JsonFeedAuthor {
+                    name: Some(name),
+                    url: None,
+                })
+                .into_iter()
+                .collect(),
+            tags: entry.categories,
+            attachments: entry
+                .enclosures
+                .into_iter()
+                .map(|e| JsonFeedAttachment {
+                    url: e.url,
+                    mime_type: e.mime_type.unwrap_or_else(|| "application/octet-stream".to_owned()),
+                    size_in_bytes: e.length,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Deserializes the `id` of an item, which may be a number to be coerced to a
+/// string according to JSON Feed 1.1
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(crate = "rocket::serde", untagged)]
+    enum Id {
+        String(String),
+        Number(serde_json::Number),
+    }
+
+    Ok(match Id::deserialize(deserializer)? {
+        Id::String(id) => id,
+        Id::Number(id) => id.to_string(),
+    })
+}
+
+fn truncate_title(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(DERIVED_TITLE_LENGTH) {
+        Some((i, _)) => format!("{}…", &line[..i]),
+        None => line.to_owned(),
+    }
+}
@@ -0,0 +1,313 @@
+use crate::error::InvalidFilterError;
+
+type Result<T> = std::result::Result<T, InvalidFilterError>;
+
+/// Deepest nesting of parentheses and negations, which bounds the recursion
+/// of parsing and matching
+pub const MAX_DEPTH: usize = 32;
+
+/// Boolean expression of keywords, e.g. `"1080p" (简体 OR 繁體) -合集`
+///
+/// - Space-separated terms must all match, `AND` may be written explicitly
+/// - `OR` matches either side, and binds looser than `AND`
+/// - `-term` or `NOT term` excludes
+/// - `"quoted phrases"` may contain spaces and operators
+/// - Parentheses group sub-expressions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeywordQuery {
+    Term(String),
+    Not(Box<KeywordQuery>),
+    And(Vec<KeywordQuery>),
+    Or(Vec<KeywordQuery>),
+}
+
+impl KeywordQuery {
+    /// Parses `input`. Returns `None` if it has no terms at all, i.e. nothing
+    /// should be filtered.
+    pub fn parse(input: &str) -> Result<Option<Self>> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let query = parser.parse_or()?;
+        match parser.next() {
+            Some((token, column)) => Err(InvalidFilterError::UnexpectedToken(token.to_string(), column)),
+            None => Ok(Some(query)),
+        }
+    }
+
+    /// Builds the query of keywords stored before the syntax above, which are
+    /// words separated by spaces that must all match literally
+    pub fn parse_legacy(input: &str) -> Option<Self> {
+        let terms: Vec<_> = input
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .map(|s| KeywordQuery::Term(s.to_owned()))
+            .collect();
+        (!terms.is_empty()).then(|| flatten(terms, KeywordQuery::And))
+    }
+
+    /// Replaces every term with `f(term)`
+    pub fn map_terms(self, f: &impl Fn(&str) -> String) -> Self {
+        match self {
+            KeywordQuery::Term(term) => KeywordQuery::Term(f(&term)),
+            KeywordQuery::Not(query) => KeywordQuery::Not(Box::new(query.map_terms(f))),
+            KeywordQuery::And(queries) => KeywordQuery::And(queries.into_iter().map(|q| q.map_terms(f)).collect()),
+            KeywordQuery::Or(queries) => KeywordQuery::Or(queries.into_iter().map(|q| q.map_terms(f)).collect()),
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            KeywordQuery::Term(term) => text.contains(term.as_str()),
+            KeywordQuery::Not(query) => !query.matches(text),
+            KeywordQuery::And(queries) => queries.iter().all(|q| q.matches(text)),
+            KeywordQuery::Or(queries) => queries.iter().any(|q| q.matches(text)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LParen => f.write_str("("),
+            Token::RParen => f.write_str(")"),
+            Token::And => f.write_str("AND"),
+            Token::Or => f.write_str("OR"),
+            Token::Not => f.write_str("NOT"),
+            Token::Term(term) => f.write_str(term),
+        }
+    }
+}
+
+/// Splits `input` into tokens along with their (1-based) columns
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let column = i + 1;
+        match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => {
+                tokens.push((Token::LParen, column));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::RParen, column));
+                i += 1;
+            }
+            // A leading `-` negates the following term, while a lone `-` is a term itself
+            '-' if chars.get(i + 1).map_or(false, |c| !c.is_whitespace() && *c != ')') => {
+                tokens.push((Token::Not, column));
+                i += 1;
+            }
+            '"' => {
+                let len = chars[i + 1..]
+                    .iter()
+                    .position(|c| *c == '"')
+                    .ok_or(InvalidFilterError::UnclosedQuote(column))?;
+                if len == 0 {
+                    return Err(InvalidFilterError::EmptyPhrase(column));
+                }
+                let phrase = chars[i + 1..i + 1 + len].iter().collect();
+                tokens.push((Token::Term(phrase), column));
+                i += len + 2;
+            }
+            _ => {
+                let len = chars[i..]
+                    .iter()
+                    .position(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+                    .unwrap_or(chars.len() - i);
+                let word: String = chars[i..i + len].iter().collect();
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Term(word),
+                };
+                tokens.push((token, column));
+                i += len;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+
+    /// Parentheses and negations enclosing the current token
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Runs `parse` one level deeper, unless that exceeds [`MAX_DEPTH`]
+    fn nested<T>(&mut self, column: usize, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(InvalidFilterError::TooDeep(column));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// `and_expr ("OR" and_expr)*`
+    fn parse_or(&mut self) -> Result<KeywordQuery> {
+        let mut queries = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            queries.push(self.parse_and()?);
+        }
+        Ok(flatten(queries, KeywordQuery::Or))
+    }
+
+    /// `unary (["AND"] unary)*`
+    fn parse_and(&mut self) -> Result<KeywordQuery> {
+        let mut queries = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                Some(Token::Term(_) | Token::Not | Token::LParen) => {}
+                _ => break,
+            }
+            queries.push(self.parse_unary()?);
+        }
+        Ok(flatten(queries, KeywordQuery::And))
+    }
+
+    /// `("NOT" | "-") unary | "(" or_expr ")" | term`
+    fn parse_unary(&mut self) -> Result<KeywordQuery> {
+        let last_column = self.tokens.last().map_or(0, |(_, column)| *column);
+        match self.next() {
+            Some((Token::Not, column)) => {
+                let query = self.nested(column, Self::parse_unary)?;
+                Ok(KeywordQuery::Not(Box::new(query)))
+            }
+            Some((Token::Term(term), _)) => Ok(KeywordQuery::Term(term)),
+            Some((Token::LParen, column)) => {
+                let query = self.nested(column, Self::parse_or)?;
+                match self.next() {
+                    Some((Token::RParen, _)) => Ok(query),
+                    _ => Err(InvalidFilterError::UnclosedParenthesis(column)),
+                }
+            }
+            Some((token, column)) => Err(InvalidFilterError::UnexpectedToken(token.to_string(), column)),
+            None => Err(InvalidFilterError::MissingKeyword(last_column)),
+        }
+    }
+}
+
+/// Collapses a single-element list instead of wrapping it with `op`
+fn flatten(mut queries: Vec<KeywordQuery>, op: fn(Vec<KeywordQuery>) -> KeywordQuery) -> KeywordQuery {
+    if queries.len() == 1 {
+        queries.pop().unwrap()
+    } else {
+        op(queries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(s: &str) -> KeywordQuery {
+        KeywordQuery::Term(s.to_owned())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(KeywordQuery::parse("  ").unwrap(), None);
+        assert_eq!(
+            KeywordQuery::parse("Rust 1.61").unwrap(),
+            Some(KeywordQuery::And(vec![term("Rust"), term("1.61")]))
+        );
+        assert_eq!(
+            KeywordQuery::parse("\"1080p\" (简体 OR 繁體) -合集").unwrap(),
+            Some(KeywordQuery::And(vec![
+                term("1080p"),
+                KeywordQuery::Or(vec![term("简体"), term("繁體")]),
+                KeywordQuery::Not(Box::new(term("合集"))),
+            ]))
+        );
+        assert_eq!(
+            KeywordQuery::parse("a b OR NOT c AND \"d OR e\"").unwrap(),
+            Some(KeywordQuery::Or(vec![
+                KeywordQuery::And(vec![term("a"), term("b")]),
+                KeywordQuery::And(vec![KeywordQuery::Not(Box::new(term("c"))), term("d OR e")]),
+            ]))
+        );
+        assert_eq!(
+            KeywordQuery::parse("x-264 - a").unwrap(),
+            Some(KeywordQuery::And(vec![term("x-264"), term("-"), term("a")]))
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy() {
+        assert_eq!(KeywordQuery::parse_legacy(" "), None);
+        assert_eq!(
+            KeywordQuery::parse_legacy("-合集 \"1080p OR (a"),
+            Some(KeywordQuery::And(vec![
+                term("-合集"),
+                term("\"1080p"),
+                term("OR"),
+                term("(a")
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let error = |s: &str| KeywordQuery::parse(s).unwrap_err().to_string();
+        assert_eq!(error("a \"b"), "unclosed quote at column 3");
+        assert_eq!(error("a (b OR c"), "unclosed parenthesis at column 3");
+        assert_eq!(error("a OR"), "missing keyword after column 3");
+        assert_eq!(error("a ) b"), "unexpected `)` at column 3");
+        assert_eq!(error("OR a"), "unexpected `OR` at column 1");
+        assert_eq!(error("a \"\""), "empty phrase at column 3");
+        assert_eq!(error(&"(".repeat(10_000)), "nested too deeply at column 33");
+        assert_eq!(error(&"NOT ".repeat(10_000)), "nested too deeply at column 129");
+        let nested = format!("{}a{}", "(-".repeat(MAX_DEPTH / 2), ")".repeat(MAX_DEPTH / 2));
+        assert!(KeywordQuery::parse(&nested).is_ok());
+    }
+
+    #[test]
+    fn test_matches() {
+        let query = KeywordQuery::parse("\"1080p\" (简体 OR 繁體) -合集").unwrap().unwrap();
+        assert!(query.matches("[Lilith-Raws] Spy x Family - 07 [Baha][WEB-DL][1080p][AVC AAC][简体]"));
+        assert!(query.matches("[Lilith-Raws] Spy x Family - 07 [1080p][繁體]"));
+        assert!(!query.matches("[Lilith-Raws] Spy x Family - 07 [720p][简体]"));
+        assert!(!query.matches("[Lilith-Raws] Spy x Family [01-12 合集][1080p][简体]"));
+    }
+}
@@ -1,60 +1,147 @@
-use std::time::Instant;
+use std::time::Duration;
 
-use futures::future::try_join_all;
-use futures::TryFutureExt;
+use chrono_tz::Tz;
 use rand::Rng;
+use reqwest::{header, StatusCode};
 
 use crate::error::{Error, Result};
 use crate::model::{FeedInfo, SourceFeed};
-use crate::util::feed_merger::FeedMerger;
+pub use crate::util::feed_entry::FeedEntry;
+pub use crate::util::feed_filter::FeedFilter;
+pub use crate::util::feed_merger::{EntrySource, FeedMerger};
 use crate::util::feed_parser::FeedDocument;
+pub use crate::util::password::{hash_password, verify_password, PasswordMatch, DUMMY_HASH};
+pub use crate::util::rate_limiter::RateLimiter;
+pub use crate::util::validation::{validate_email, validate_password};
 
+mod date_parser;
+mod encoding;
+mod feed_entry;
+mod feed_filter;
 mod feed_merger;
 mod feed_parser;
+mod json_feed;
+mod keyword_query;
+mod password;
+mod rate_limiter;
+mod validation;
 
-pub async fn fetch_rss_info(url: &str, limit: usize) -> Result<FeedInfo> {
-    const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
-    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
-    let resp = client.get(url).send().await?;
+/// Timeout of fetching one upstream feed
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
 
-    if resp.status().is_success() {
-        let data = resp.bytes().await?;
-        let doc = FeedDocument::parse(data.as_ref())?.with_limit(limit);
-        let feed_info = doc.read_feed()?;
-        Ok(feed_info)
-    } else {
-        Err(Error::FetchFeedStatus(resp.status()))
-    }
+/// `ETag` and `Last-Modified` of a previous response, sent back upstream as
+/// `If-None-Match` and `If-Modified-Since`
+#[derive(Debug, Clone, Default)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
 
-pub async fn merge_feeds_data(feeds: &[SourceFeed], limit: usize) -> Result<Vec<u8>> {
-    let mut merger = FeedMerger::new();
+/// Result of a conditional fetch
+pub enum FetchedFeed {
+    /// Upstream answered `304 Not Modified`, so the stored entries are still
+    /// up to date
+    NotModified,
 
-    let start_time = Instant::now();
-    let futures = feeds
-        .iter()
-        .map(|feed| reqwest::get(&feed.url).and_then(|resp| resp.bytes()));
-    let responses = try_join_all(futures).await?;
+    Modified {
+        entries: Vec<FeedEntry>,
+        validators: CacheValidators,
+    },
+}
 
-    info_!("Fetching {} feeds costs {:?}", feeds.len(), start_time.elapsed());
+/// Fetches `feed` and returns at most `limit` items passing its filter
+pub async fn fetch_rss_info(feed: &SourceFeed, limit: usize) -> Result<FeedInfo> {
+    let filter = FeedFilter::new(feed)?;
+    let timezone = feed_timezone(feed)?;
+    let client = http_client()?;
+    let (doc, _) = fetch_document(&client, &feed.url, &CacheValidators::default())
+        .await?
+        .ok_or(Error::FetchFeedStatus(StatusCode::NOT_MODIFIED))?;
+    let feed_info = doc
+        .with_filter(filter)
+        .with_timezone(timezone)
+        .with_limit(limit)
+        .read_feed()?;
+    Ok(feed_info)
+}
+
+/// Fetches `feed` and returns at most `limit` entries passing its filter,
+/// unless upstream reports it unchanged since the response of `validators`
+pub async fn fetch_feed_entries(
+    client: &reqwest::Client,
+    feed: &SourceFeed,
+    validators: &CacheValidators,
+    limit: usize,
+) -> Result<FetchedFeed> {
+    let (doc, validators) = match fetch_document(client, &feed.url, validators).await? {
+        Some(fetched) => fetched,
+        None => return Ok(FetchedFeed::NotModified),
+    };
+    let filter = FeedFilter::new(feed)?;
+    let timezone = feed_timezone(feed)?;
+    let entries = doc
+        .with_filter(filter)
+        .with_timezone(timezone)
+        .with_limit(limit)
+        .into_entries()?;
+    Ok(FetchedFeed::Modified { entries, validators })
+}
 
-    for (feed, text) in feeds.iter().zip(responses.iter()) {
-        let keywords = split_keywords(&feed.keywords);
-        let doc = FeedDocument::parse(text.as_ref())?
-            .with_keywords(keywords)
-            .with_limit(limit);
-        merger.append(doc)?;
+/// Returns the timezone of dates without offsets in `feed`, which is UTC
+/// unless specified
+pub fn feed_timezone(feed: &SourceFeed) -> Result<Tz> {
+    match feed.timezone.as_deref().filter(|tz| !tz.is_empty()) {
+        Some(name) => name.parse().map_err(|_| Error::InvalidTimezone(name.to_owned())),
+        None => Ok(Tz::UTC),
+    }
+}
+
+pub fn http_client() -> Result<reqwest::Client> {
+    const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(FETCH_TIMEOUT)
+        .build()?;
+    Ok(client)
+}
+
+/// Fetches the document at `url` conditionally. Returns `None` on `304 Not
+/// Modified`.
+async fn fetch_document(
+    client: &reqwest::Client,
+    url: &str,
+    validators: &CacheValidators,
+) -> Result<Option<(FeedDocument, CacheValidators)>> {
+    let mut req = client.get(url);
+    if let Some(etag) = &validators.etag {
+        req = req.header(header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        req = req.header(header::IF_MODIFIED_SINCE, last_modified);
+    }
+    let resp = req.send().await?;
+
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        Ok(None)
+    } else if resp.status().is_success() {
+        let content_type = header_value(&resp, header::CONTENT_TYPE);
+        let validators = CacheValidators {
+            etag: header_value(&resp, header::ETAG),
+            last_modified: header_value(&resp, header::LAST_MODIFIED),
+        };
+        let data = resp.bytes().await?;
+        let doc = FeedDocument::parse_with_content_type(data.as_ref(), content_type.as_deref())?;
+        Ok(Some((doc, validators)))
+    } else {
+        Err(Error::FetchFeedStatus(resp.status()))
     }
-    let out = merger.build();
-    Ok(out)
 }
 
-fn split_keywords(keywords: &str) -> Vec<String> {
-    keywords
-        .split(' ')
-        .filter(|s| !s.is_empty())
+fn header_value(resp: &reqwest::Response, name: header::HeaderName) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
         .map(|s| s.to_owned())
-        .collect()
 }
 
 pub fn gen_rand_token() -> String {
@@ -0,0 +1,130 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::rngs::OsRng;
+use rocket::tokio::task;
+
+use crate::error::{Error, Result};
+
+/// Static salt of the SHA-256 hashes stored before Argon2id was adopted
+const LEGACY_SALT: &str = "merge into one rss!";
+
+/// Memory cost of Argon2id in KiB, along with the time and parallelism costs
+/// below, as recommended by OWASP
+const ARGON2_M_COST: u32 = 19 * 1024;
+
+const ARGON2_T_COST: u32 = 2;
+
+const ARGON2_P_COST: u32 = 1;
+
+/// Hash with the current costs to verify against when the user is not found,
+/// so that unregistered emails can't be told apart by the response time
+pub const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$AN8RRVFdPdpzt/v17tsw0w$GttHcWbEfseEG19HG9fAA2Zw9z86aLP2MFTncuKMDzI";
+
+/// Result of checking a password against the stored hash
+#[derive(Debug, PartialEq, Eq)]
+pub enum PasswordMatch {
+    Mismatch,
+
+    Match,
+
+    /// Matches a legacy SHA-256 hash, or an Argon2 hash with other costs than
+    /// the current ones, which should be replaced with [`hash_password`]
+    Legacy,
+}
+
+/// Hashes `password` with Argon2id and a random salt, into a PHC string like
+/// `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`. Hashing takes a while by
+/// design, so it runs on a blocking thread.
+pub async fn hash_password(password: &str) -> Result<String> {
+    let password = password.to_owned();
+    task::spawn_blocking(move || hash(&password))
+        .await
+        .map_err(|e| Error::Custom(e.to_string()))?
+}
+
+/// Checks `password` against `users.password`, which is either a PHC string
+/// or the raw digest of a legacy hash, on a blocking thread
+pub async fn verify_password(password: &str, hashed: &[u8]) -> Result<PasswordMatch> {
+    let password = password.to_owned();
+    let hashed = hashed.to_owned();
+    task::spawn_blocking(move || verify(&password, &hashed))
+        .await
+        .map_err(|e| Error::Custom(e.to_string()))
+}
+
+fn argon2() -> Argon2<'static> {
+    let params = Params::new(ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST, None).unwrap();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+fn hash(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::Custom(e.to_string()))
+}
+
+fn verify(password: &str, hashed: &[u8]) -> PasswordMatch {
+    let phc = std::str::from_utf8(hashed)
+        .ok()
+        .and_then(|hashed| PasswordHash::new(hashed).ok());
+    match phc {
+        // The costs of the hash are used instead of the current ones
+        Some(phc) => match argon2().verify_password(password.as_bytes(), &phc) {
+            Ok(()) if is_outdated(&phc) => PasswordMatch::Legacy,
+            Ok(()) => PasswordMatch::Match,
+            Err(_) => PasswordMatch::Mismatch,
+        },
+        None if legacy_hash(password) == hashed => PasswordMatch::Legacy,
+        None => PasswordMatch::Mismatch,
+    }
+}
+
+fn is_outdated(phc: &PasswordHash) -> bool {
+    match Params::try_from(phc) {
+        Ok(params) => {
+            phc.algorithm != Algorithm::Argon2id.ident()
+                || (params.m_cost(), params.t_cost(), params.p_cost()) != (ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST)
+        }
+        Err(_) => true,
+    }
+}
+
+fn legacy_hash(password: &str) -> Vec<u8> {
+    use sha2::Digest;
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(password.as_bytes());
+    hasher.update(LEGACY_SALT.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_password() {
+        let hashed = hash("hunter2").unwrap();
+        assert!(hashed.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+        assert_eq!(verify("hunter2", hashed.as_bytes()), PasswordMatch::Match);
+        assert_eq!(verify("hunter3", hashed.as_bytes()), PasswordMatch::Mismatch);
+        // Salts are random
+        assert_ne!(hashed, hash("hunter2").unwrap());
+
+        let legacy = legacy_hash("hunter2");
+        assert_eq!(verify("hunter2", &legacy), PasswordMatch::Legacy);
+        assert_eq!(verify("hunter3", &legacy), PasswordMatch::Mismatch);
+
+        // Default costs of argon2 0.4
+        let salt = SaltString::generate(&mut OsRng);
+        let weak = Argon2::default().hash_password(b"hunter2", &salt).unwrap().to_string();
+        assert_eq!(verify("hunter2", weak.as_bytes()), PasswordMatch::Legacy);
+        assert_eq!(verify("hunter3", weak.as_bytes()), PasswordMatch::Mismatch);
+
+        let dummy = PasswordHash::new(DUMMY_HASH).unwrap();
+        assert!(!is_outdated(&dummy));
+        assert_eq!(verify("hunter2", DUMMY_HASH.as_bytes()), PasswordMatch::Mismatch);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+
+/// Longest lockout, however many times it has doubled
+const MAX_LOCKOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Failures of a key are forgotten after this long without new ones
+const FORGET_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of tracked keys above which forgotten ones are dropped
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Failures of one key, e.g. a client IP or an email
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Locks keys out after too many failures (e.g. incorrect passwords), for a
+/// period doubled by every further failure
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Failures allowed before the first lockout
+    max_failures: u32,
+
+    /// Period of the first lockout
+    base_lockout: Duration,
+
+    failures: Mutex<HashMap<String, Failures>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_failures: u32, base_lockout: Duration) -> Self {
+        Self {
+            max_failures,
+            base_lockout,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns [`Error::TooManyAttempts`] if `key` is locked out
+    pub fn check(&self, key: &str) -> Result<()> {
+        self.check_at(key, Instant::now())
+    }
+
+    pub fn record_failure(&self, key: &str) {
+        self.record_failure_at(key, Instant::now())
+    }
+
+    /// Forgets the failures of `key`, e.g. after a successful login
+    pub fn reset(&self, key: &str) {
+        self.failures.lock().unwrap().remove(key);
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<()> {
+        let failures = self.failures.lock().unwrap();
+        match failures.get(key).and_then(|f| f.locked_until) {
+            Some(locked_until) if locked_until > now => {
+                // Rounded up, so that a retry after it always passes
+                let retry_after = locked_until - now + Duration::from_millis(999);
+                Err(Error::TooManyAttempts(retry_after.as_secs()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&self, key: &str, now: Instant) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() >= PRUNE_THRESHOLD {
+            failures.retain(|_, f| now - f.last_failure < FORGET_AFTER);
+        }
+
+        let f = failures.entry(key.to_owned()).or_insert(Failures {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        if now - f.last_failure >= FORGET_AFTER {
+            f.count = 0;
+        }
+        f.count += 1;
+        f.last_failure = now;
+        if f.count >= self.max_failures {
+            let doublings = (f.count - self.max_failures).min(16);
+            let lockout = (self.base_lockout * 2u32.pow(doublings)).min(MAX_LOCKOUT);
+            f.locked_until = Some(now + lockout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout() {
+        let limiter = RateLimiter::new(3, Duration::from_secs(10));
+        let start = Instant::now();
+        limiter.record_failure_at("a", start);
+        limiter.record_failure_at("a", start);
+        assert!(limiter.check_at("a", start).is_ok());
+
+        limiter.record_failure_at("a", start);
+        assert!(matches!(limiter.check_at("a", start), Err(Error::TooManyAttempts(10))));
+        assert!(limiter.check_at("b", start).is_ok());
+        let t = start + Duration::from_secs(10);
+        assert!(limiter.check_at("a", t).is_ok());
+
+        // Every further failure doubles the lockout
+        limiter.record_failure_at("a", t);
+        assert!(matches!(limiter.check_at("a", t), Err(Error::TooManyAttempts(20))));
+        let t = t + Duration::from_secs(20);
+        limiter.record_failure_at("a", t);
+        assert!(matches!(limiter.check_at("a", t), Err(Error::TooManyAttempts(40))));
+        for _ in 0..20 {
+            limiter.record_failure_at("a", t);
+        }
+        assert!(matches!(limiter.check_at("a", t), Err(Error::TooManyAttempts(3600))));
+
+        limiter.reset("a");
+        assert!(limiter.check_at("a", t).is_ok());
+    }
+
+    #[test]
+    fn test_forget_failures() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+        limiter.record_failure_at("a", start);
+        limiter.record_failure_at("a", start + FORGET_AFTER);
+        assert!(limiter.check_at("a", start + FORGET_AFTER).is_ok());
+    }
+}
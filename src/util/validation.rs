@@ -0,0 +1,71 @@
+use crate::error::{Error, Result};
+
+/// Max length of an email address, as limited by SMTP
+const MAX_EMAIL_LENGTH: usize = 254;
+
+const MIN_PASSWORD_LENGTH: usize = 6;
+
+/// Passwords are bounded so that hashing them stays cheap
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Checks that `email` looks like `local@example.com`. Whether it exists is
+/// left to the mail server.
+pub fn validate_email(email: &str) -> Result<()> {
+    let valid = email.len() <= MAX_EMAIL_LENGTH
+        && !email.contains(|c: char| c.is_whitespace() || c.is_control())
+        && match email.rsplit_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && domain.contains('.')
+                    && domain
+                        .split('.')
+                        .all(|label| !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-'))
+            }
+            None => false,
+        };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidEmail(email.to_owned()))
+    }
+}
+
+/// Checks the length (in chars) of a new password
+pub fn validate_password(password: &str) -> Result<()> {
+    if (MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&password.chars().count()) {
+        Ok(())
+    } else {
+        Err(Error::InvalidPassword {
+            min: MIN_PASSWORD_LENGTH,
+            max: MAX_PASSWORD_LENGTH,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_email() {
+        assert!(validate_email("a@example.com").is_ok());
+        assert!(validate_email("first.last+tag@mail.example.co.jp").is_ok());
+        assert!(validate_email("用户@例子.中国").is_ok());
+
+        assert!(validate_email("").is_err());
+        assert!(validate_email("example.com").is_err());
+        assert!(validate_email("@example.com").is_err());
+        assert!(validate_email("a@localhost").is_err());
+        assert!(validate_email("a@example..com").is_err());
+        assert!(validate_email("a b@example.com").is_err());
+        assert!(validate_email(&format!("{}@example.com", "a".repeat(250))).is_err());
+    }
+
+    #[test]
+    fn test_validate_password() {
+        assert!(validate_password("123456").is_ok());
+        assert!(validate_password("密码密码密码").is_ok());
+        assert!(validate_password("12345").is_err());
+        assert!(validate_password(&"a".repeat(129)).is_err());
+    }
+}